@@ -0,0 +1,171 @@
+use crate::error::{MsqError, Result};
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::str::FromStr;
+
+/// IPv4 address of a server, as sent by the master server
+///
+/// Ordered like the numeric IPv4 address (octet by octet). Four bytes,
+/// copied when passed by value.
+///
+/// ```
+/// use msq::Address;
+///
+/// let address = Address { a: 1, b: 2, c: 3, d: 4 };
+/// let copy = address;
+/// assert_eq!(address, copy);
+/// assert_eq!(address.octets(), [1, 2, 3, 4]);
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Clone, Copy)]
+pub struct Address {
+    pub a: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+}
+
+pub(crate) const EMPTY_ADRESS: Address = Address {
+    a: 0,
+    b: 0,
+    c: 0,
+    d: 0,
+};
+
+impl Address {
+    /// Returns the four octets of the address, like [`Ipv4Addr::octets`]
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Address;
+    ///
+    /// let address = Address { a: 192, b: 168, c: 0, d: 1 };
+    /// assert_eq!(address.octets(), [192, 168, 0, 1]);
+    /// ```
+    pub const fn octets(&self) -> [u8; 4] {
+        [self.a, self.b, self.c, self.d]
+    }
+
+    /// Returns the address as a big-endian `u32`, like `u32::from(Ipv4Addr)`
+    ///
+    /// Handy for compact storage and range checks: addresses compare like
+    /// their `u32`.
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Address;
+    /// use std::net::Ipv4Addr;
+    ///
+    /// let address = Address { a: 192, b: 168, c: 0, d: 1 };
+    /// assert_eq!(address.to_u32(), 0xC0A8_0001);
+    /// assert_eq!(address.to_u32(), u32::from(Ipv4Addr::new(192, 168, 0, 1)));
+    ///
+    /// for text in ["0.0.0.0", "1.0.20.3", "10.255.0.7", "255.255.255.255"] {
+    ///     let address: Address = text.parse().unwrap();
+    ///     assert_eq!(Address::from_u32(address.to_u32()), address);
+    /// }
+    /// ```
+    pub const fn to_u32(&self) -> u32 {
+        u32::from_be_bytes(self.octets())
+    }
+
+    /// Returns the address of a big-endian `u32`, see [`to_u32`](#method.to_u32)
+    pub const fn from_u32(ip: u32) -> Address {
+        let [a, b, c, d] = ip.to_be_bytes();
+        Address { a, b, c, d }
+    }
+}
+
+/// Formats the address as a dotted quad (EX: `192.168.0.1`)
+///
+/// # Example
+/// ```
+/// use msq::Address;
+///
+/// let address = Address { a: 1, b: 0, c: 20, d: 3 };
+/// assert_eq!(address.to_string(), "1.0.20.3");
+/// ```
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}.{}", self.a, self.b, self.c, self.d)
+    }
+}
+
+/// Parses a dotted quad (EX: `192.168.0.1`) back into an address
+///
+/// # Example
+/// ```
+/// use msq::Address;
+///
+/// for text in ["0.0.0.0", "1.0.20.3", "192.168.0.1", "255.255.255.255"] {
+///     let address: Address = text.parse().unwrap();
+///     assert_eq!(address.to_string(), text);
+/// }
+///
+/// assert!("1.2.3".parse::<Address>().is_err());
+/// assert!("1.2.3.4.5".parse::<Address>().is_err());
+/// assert!("1.2.3.256".parse::<Address>().is_err());
+/// assert!("1.2..4".parse::<Address>().is_err());
+/// ```
+impl FromStr for Address {
+    type Err = MsqError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut octets = [0u8; 4];
+        let mut parts = s.split('.');
+        for octet in octets.iter_mut() {
+            *octet = parts
+                .next()
+                .and_then(|part| part.parse().ok())
+                .ok_or(MsqError::InvalidAddress)?;
+        }
+        if parts.next().is_some() {
+            return Err(MsqError::InvalidAddress);
+        }
+
+        Ok(Address {
+            a: octets[0],
+            b: octets[1],
+            c: octets[2],
+            d: octets[3],
+        })
+    }
+}
+
+// Parses a `a.b.c.d/len` CIDR block into its network and mask
+#[cfg(feature = "async")]
+pub(crate) fn parse_cidr(cidr: &str) -> Result<(u32, u32)> {
+    let (address, len) = cidr.split_once('/').ok_or(MsqError::InvalidAddress)?;
+    let address: Address = address.parse()?;
+    let len: u32 = match len.parse() {
+        Ok(len) if len <= 32 => len,
+        _ => return Err(MsqError::InvalidAddress),
+    };
+    let mask = u32::MAX.checked_shl(32 - len).unwrap_or(0);
+    Ok((address.to_u32() & mask, mask))
+}
+
+impl From<Address> for Ipv4Addr {
+    fn from(address: Address) -> Self {
+        Ipv4Addr::new(address.a, address.b, address.c, address.d)
+    }
+}
+
+/// Maps query results into [`SocketAddrV4`]s, ready for A2S queries
+///
+/// # Example
+/// ```
+/// use msq::{to_socket_addrs, Address};
+/// use std::net::SocketAddrV4;
+///
+/// let servers = vec![(Address { a: 1, b: 2, c: 3, d: 4 }, 27015)];
+/// let addrs: Vec<SocketAddrV4> = to_socket_addrs(servers).collect();
+/// assert_eq!(addrs, vec!["1.2.3.4:27015".parse().unwrap()]);
+/// ```
+pub fn to_socket_addrs<I>(servers: I) -> impl Iterator<Item = SocketAddrV4>
+where
+    I: IntoIterator<Item = (Address, u16)>,
+{
+    servers
+        .into_iter()
+        .map(|(address, port)| SocketAddrV4::new(address.into(), port))
+}
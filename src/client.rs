@@ -1,33 +1,98 @@
+use crate::address::{self, Address};
+use crate::dedup::{DedupMode, Seen};
+use crate::error::{MsqError, Result};
 use crate::filter::Filter;
+use crate::packet::{self, Page, Reply, Seed};
 use crate::region::Region;
+use crate::server_list::ServerList;
 
-use crate::packet_ext::{ReadPacketExt, WritePacketExt};
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use tokio::time::sleep;
-use std::io::{Cursor, Error, ErrorKind, Result};
+use crate::packet_ext::WritePacketExt;
+#[cfg(feature = "replay")]
+use crate::replay::Recorder;
+#[cfg(feature = "socks5")]
+use crate::socks5::Socks5Relay;
+use futures_core::Stream;
+use socket2::SockRef;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::Cursor;
+use std::net::SocketAddr;
+#[cfg(feature = "replay")]
+use std::path::Path;
+use std::pin::Pin;
+#[cfg(feature = "rdns")]
+use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Duration;
-use tokio::net::UdpSocket;
-use tokio::sync::mpsc::Sender;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::net::{lookup_host, UdpSocket};
+use tokio::sync::mpsc::error::SendTimeoutError;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::time::{sleep, timeout, Instant};
+use tokio_util::sync::CancellationToken;
+
+const DEFAULT_DELAY: Duration = Duration::from_secs(6);
+const ADAPTIVE_DELAY_CAP: Duration = Duration::from_secs(60);
+const DEFAULT_SEND_TIMEOUT: Duration = Duration::from_secs(30);
+// Pages held by `query` while its channel is full, before pausing
+const FORWARD_PAGES: usize = 4;
+// Most servers seen in a single response from the Steam master server
+const FULL_PAGE_SERVERS: usize = 231;
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(10);
+const SCRAPE_RETRIES: usize = 2;
+// Every region on its own, then `All` for servers outside of them
+const SCRAPE_REGIONS: [Region; 9] = [
+    Region::USEast,
+    Region::USWest,
+    Region::SouthAmerica,
+    Region::Europe,
+    Region::Asia,
+    Region::Australia,
+    Region::MiddleEast,
+    Region::Africa,
+    Region::All,
+];
+
+#[cfg(feature = "rdns")]
+const RDNS_CONCURRENCY: usize = 16;
+
+type Resolver = Box<dyn Fn(&str) -> Result<Vec<SocketAddr>> + Send + Sync>;
+#[cfg(feature = "rdns")]
+type ReverseResolver = Arc<dyn Fn(&Address) -> Option<String> + Send + Sync>;
 
 /// The primary MSQ client driver (async)
 ///
 /// * Requires feature: `async` (Turned **on** by default)
 /// * Intended to be used with [`Filter`] and [`Region`].
 /// * This uses the [`tokio`] asynchronous UDP Socket to achieve an
-/// async MSQ client driver.
+///   async MSQ client driver.
 /// * The non-async/blocking version of this: [`MSQClientBlock`](crate::MSQClientBlock)
 ///
 /// ## Quick Start
-/// ```rust
+/// ```rust,no_run
 /// use msq::{MSQClient, Region, Filter};
 /// use std::io::Result;
+/// use tokio::sync::mpsc;
 ///
 /// #[tokio::main]
 /// async fn main() -> Result<()> {
 ///     let mut client = MSQClient::new().await?;
 ///     client.connect("hl2master.steampowered.com:27011").await?;
 ///
-///     let servers = client
+///     let (sender, mut receiver) = mpsc::channel(64);
+///     tokio::spawn(async move {
+///         while let Some(server) = receiver.recv().await {
+///             match server {
+///                 Ok((address, port)) => {
+///                     // Do something with each server
+///                 }
+///                 // The query failed, the list is incomplete
+///                 Err(err) => eprintln!("{}", err),
+///             }
+///         }
+///     });
+///
+///     client
 ///         .query(Region::Europe,  // Restrict query to Europe region
 ///             Filter::new()       // Create a Filter builder
 ///                 .appid(240)     // appid of 240 (CS:S)
@@ -35,44 +100,762 @@ use tokio::sync::mpsc::Sender;
 ///                     .map("de_dust2")     // Map is de_dust2
 ///                     .empty(true)         // Server is empty
 ///                 .end()          // End of NAND special filter
-///                 .gametype(&vec!["friendlyfire", "alltalk"])).await?;
+///                 .gametype(&vec!["friendlyfire", "alltalk"]),
+///             sender).await?;
 ///     Ok(())
 /// }
 /// ```
+///
+/// ## Challenge handshake
+/// Some master servers answer a request with a challenge
+/// (`0xFF 0xFF 0xFF 0xFF 0x41` followed by a 4 bytes little endian number)
+/// instead of a page. The client detects it and sends the same request
+/// again right away with the number appended after the filter, then keeps
+/// appending it to every following request of the query. Against such a
+/// master server the first page costs one more round trip (not delayed,
+/// but waiting for the response timeout again). A master server that keeps
+/// answering with a challenge fails the query with
+/// [`MsqError::UnexpectedResponse(0x41)`](MsqError::UnexpectedResponse)
+/// after 3 answered challenges in a row, as does a challenge without its number.
 pub struct MSQClient {
     sock: UdpSocket,
+    delay: Duration,
+    initial_timeout: Option<Duration>,
+    page_timeout: Option<Duration>,
+    send_timeout: Option<Duration>,
+    cancel: Option<CancellationToken>,
+    progress: Option<Sender<Progress>>,
+    completion: Option<Sender<QueryComplete>>,
+    master_addr: Option<String>,
+    recv_buffer: Option<usize>,
+    ttl: Option<u32>,
+    rotate_master: bool,
+    rotation: usize,
+    resolver: Option<Resolver>,
+    skip_unknown_packets: bool,
+    deadline: Option<Duration>,
+    paging: Option<Paging>,
+    max_pages: Option<usize>,
+    max_ports_per_ip: Option<usize>,
+    adaptive_delay: bool,
+    skip_delay_when_full: bool,
+    strict: bool,
+    validate: bool,
+    dedup_mode: DedupMode,
+    response_header: Vec<u8>,
+    region: Region,
+    filter: Filter,
+    bytes_sent: u64,
+    bytes_received: u64,
+    #[cfg(feature = "rdns")]
+    reverse_resolver: Option<ReverseResolver>,
+    #[cfg(feature = "replay")]
+    recorder: Option<Recorder>,
+    #[cfg(feature = "socks5")]
+    proxy: Option<Socks5Relay>,
+}
+
+// State of the query stepped through by `poll_next_page`
+struct Paging {
+    region_code: u8,
+    filter_bytes: Vec<u8>,
+    seed: Seed,
+    // Sent along every request once the master server asked for it
+    challenge: Option<u32>,
+    // Ports returned so far per address, when limited
+    ports: HashMap<Address, Vec<u16>>,
+    last_full: bool,
+    pages: usize,
+    servers: usize,
+    end_of_list: bool,
+}
+
+/// Snapshot of a running query, sent after every page received
+///
+/// See [`MSQClient::set_progress_sender`]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Progress {
+    /// Number of pages (response packets) received so far
+    pub pages: usize,
+    /// Number of servers received so far
+    pub servers: usize,
+    /// Whether the master server ended the list with this page
+    pub end_of_list: bool,
+}
+
+impl Progress {
+    /// Returns the average number of servers per page received so far
+    pub fn average_page_size(&self) -> f64 {
+        if self.pages == 0 {
+            0.0
+        } else {
+            self.servers as f64 / self.pages as f64
+        }
+    }
+
+    /// Returns a rough estimate of how far along the query is, from `0.0` to `1.0`
+    ///
+    /// **NOTE**: This is a best-effort estimate, not exact. The protocol gives
+    /// no total, so until the end of the list this assumes one more page of
+    /// the average size is left: it grows with every page but only reaches
+    /// `1.0` once the list ended.
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Progress;
+    ///
+    /// let progress = Progress { pages: 3, servers: 300, end_of_list: false };
+    /// assert_eq!(progress.estimated_progress(), 0.75);
+    ///
+    /// let progress = Progress { pages: 4, servers: 320, end_of_list: true };
+    /// assert_eq!(progress.estimated_progress(), 1.0);
+    /// ```
+    pub fn estimated_progress(&self) -> f64 {
+        if self.end_of_list {
+            1.0
+        } else if self.servers == 0 {
+            0.0
+        } else {
+            self.servers as f64 / (self.servers as f64 + self.average_page_size())
+        }
+    }
+}
+
+/// Sent once the master server ended the list of a query
+///
+/// See [`MSQClient::set_completion_sender`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct QueryComplete {
+    /// Number of servers received, duplicates included
+    pub count: usize,
+    /// The last server of the list, seed of the final request
+    /// (`0.0.0.0:0` when the list was empty)
+    pub last_seed: (Address, u16),
+}
+
+/// Options of a single query, see [`MSQClient::query_with`]
+///
+/// Bundles the settings of a one-off query in one place, they only apply
+/// to that query. The client's setters remain for settings shared by every
+/// query of the client.
+///
+/// | Field         | Default | Meaning                                              |
+/// | ------------- | ------- | ---------------------------------------------------- |
+/// | `delay`       | 6s      | Delay between each request                           |
+/// | `timeout`     | `None`  | Time to wait for every response, `None` = forever    |
+/// | `retries`     | `0`     | Requests of a page timing out sent again, in total   |
+/// | `max_results` | `None`  | Stop once this many servers were sent                |
+/// | `dedup`       | `None`  | Drop servers already sent, `None` = send every copy  |
+///
+/// # Example
+/// ```
+/// use msq::{DedupMode, QueryOpts};
+/// use std::time::Duration;
+///
+/// let opts = QueryOpts {
+///     timeout: Some(Duration::from_secs(5)),
+///     retries: 2,
+///     dedup: Some(DedupMode::Exact),
+///     ..QueryOpts::default()
+/// };
+/// assert_eq!(opts.delay, Duration::from_secs(6));
+/// assert_eq!(opts.max_results, None);
+/// ```
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct QueryOpts {
+    /// Delay between each request sent to the master server
+    pub delay: Duration,
+    /// Time to wait for every response, `None` to wait forever
+    pub timeout: Option<Duration>,
+    /// Number of times a request which timed out is sent again, for the
+    /// whole query
+    pub retries: usize,
+    /// Maximum number of servers sent, the query stops once reached
+    pub max_results: Option<usize>,
+    /// How servers sent more than once are dropped, `None` to send them all
+    pub dedup: Option<DedupMode>,
+}
+
+impl Default for QueryOpts {
+    fn default() -> Self {
+        QueryOpts {
+            delay: DEFAULT_DELAY,
+            timeout: None,
+            retries: 0,
+            max_results: None,
+            dedup: None,
+        }
+    }
+}
+
+/// Statistics of a query run by [`MSQClient::query_with`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct QueryStats {
+    /// Number of servers sent
+    pub servers: usize,
+    /// Number of servers dropped as already sent
+    pub duplicates: usize,
+    /// Number of pages (response packets) received, keepalive pages included
+    pub pages: usize,
+    /// Number of requests sent again after a timeout
+    pub retries: usize,
+    /// Whether the master server ended the list, `false` when the query
+    /// stopped early (EX: `max_results` reached)
+    pub terminated: bool,
+    /// Time the whole query took
+    pub elapsed: Duration,
+}
+
+/// Every server found by [`MSQClient::query_vec`], and how the query ended
+///
+/// A query can stop before the master server ended the list without any
+/// error (EX: [`MSQClient::set_max_pages`]). An empty `servers` only means
+/// that nothing matches the query when `terminated` is `true`.
+#[derive(Debug, Default, Clone)]
+pub struct QueryResult {
+    /// The servers found, without duplicates
+    pub servers: ServerList,
+    /// Number of pages (response packets) received, keepalive pages included
+    pub pages: usize,
+    /// Whether the master server ended the list, `false` when the query
+    /// stopped early and the list is incomplete
+    pub terminated: bool,
 }
 
-#[derive(PartialEq, Default, Clone)]
-pub struct Address {
-    pub a: u8,
-    pub b: u8,
-    pub c: u8,
-    pub d: u8,
+/// How a query that reached the end of the server list finished
+///
+/// The master server answers a filter (or region) matching nothing with an
+/// immediately terminated list. This is a successful query, not an error,
+/// and is told apart from a query that found servers.
+///
+/// # Example
+/// ```
+/// use msq::QueryOutcome;
+///
+/// assert_eq!(QueryOutcome::from_count(0), QueryOutcome::Empty);
+/// assert_eq!(QueryOutcome::from_count(3).servers(), 3);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum QueryOutcome {
+    /// Completed without any server matching the query
+    Empty,
+    /// Completed normally, with the number of servers sent
+    Completed(usize),
 }
 
-const EMPTY_ADRESS: Address = Address {
-    a: 0,
-    b: 0,
-    c: 0,
-    d: 0,
-};
+impl QueryOutcome {
+    /// Returns the outcome of a completed query which sent `count` servers
+    pub fn from_count(count: usize) -> QueryOutcome {
+        if count == 0 {
+            Self::Empty
+        } else {
+            Self::Completed(count)
+        }
+    }
+
+    /// Returns the number of servers sent
+    pub fn servers(&self) -> usize {
+        match self {
+            Self::Empty => 0,
+            Self::Completed(count) => *count,
+        }
+    }
+}
 
 impl MSQClient {
     /// Create a new MSQClient variable and binds the UDP socket to `0.0.0.0:0`
     pub async fn new() -> Result<MSQClient> {
         let sock = UdpSocket::bind("0.0.0.0:0").await?;
-        Ok(MSQClient { sock })
+        Ok(Self::from_socket(sock))
     }
 
-    /// Connect the client to the given master server address/hostname
+    /// Create a new MSQClient variable from an existing UDP socket
+    ///
+    /// Allows configuring the socket beforehand (EX: binding a specific
+    /// interface or setting options not covered by the client). If the
+    /// socket is already connected, its peer becomes the master server
+    /// used by [`reconnect`](#method.reconnect), and the client is ready
+    /// to query without calling [`connect`](#method.connect).
     ///
     /// # Arguments
-    /// * `master_server_addr` - The master server's hostname/ip address
+    /// * `sock` - A bound [`tokio::net::UdpSocket`], optionally connected
+    pub fn from_socket(sock: UdpSocket) -> MSQClient {
+        let master_addr = sock.peer_addr().ok().map(|addr| addr.to_string());
+        MSQClient {
+            sock,
+            delay: DEFAULT_DELAY,
+            initial_timeout: None,
+            page_timeout: None,
+            send_timeout: Some(DEFAULT_SEND_TIMEOUT),
+            cancel: None,
+            progress: None,
+            completion: None,
+            master_addr,
+            recv_buffer: None,
+            ttl: None,
+            rotate_master: false,
+            rotation: 0,
+            resolver: None,
+            skip_unknown_packets: false,
+            deadline: None,
+            paging: None,
+            max_pages: None,
+            max_ports_per_ip: None,
+            adaptive_delay: false,
+            skip_delay_when_full: false,
+            strict: false,
+            validate: true,
+            dedup_mode: DedupMode::Exact,
+            response_header: packet::RESPONSE_HEADER.to_vec(),
+            region: Region::All,
+            filter: Filter::new(),
+            bytes_sent: 0,
+            bytes_received: 0,
+            #[cfg(feature = "rdns")]
+            reverse_resolver: None,
+            #[cfg(feature = "replay")]
+            recorder: None,
+            #[cfg(feature = "socks5")]
+            proxy: None,
+        }
+    }
+
+    /// Returns the local address the client's socket is bound to
+    ///
+    /// # Example
+    /// ```
+    /// use msq::MSQClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> msq::Result<()> {
+    ///     let client = MSQClient::new().await?;
+    ///     assert_ne!(client.local_addr()?.port(), 0);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.sock.local_addr()?)
+    }
+
+    /// Returns the number of bytes sent to the master server so far
+    ///
+    /// Counts the payload of every datagram sent (as wrapped for the SOCKS5
+    /// relay, if any), across every query and reconnection of the client
+    /// since it was created or since [`reset_byte_counts`](#method.reset_byte_counts).
+    /// IP and UDP headers are not included.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// Returns the number of bytes received so far, see [`bytes_sent`](#method.bytes_sent)
+    ///
+    /// Every datagram received counts, including the ones skipped as
+    /// unknown (see [`set_skip_unknown_packets`](#method.set_skip_unknown_packets)).
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Set both byte counts back to zero
     ///
     /// # Example
     /// ```
     /// use msq::MSQClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> msq::Result<()> {
+    ///     let mut client = MSQClient::new().await?;
+    ///     client.reset_byte_counts();
+    ///     assert_eq!((client.bytes_sent(), client.bytes_received()), (0, 0));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn reset_byte_counts(&mut self) {
+        self.bytes_sent = 0;
+        self.bytes_received = 0;
+    }
+
+    /// Set the delay between each request sent to the master server
+    ///
+    /// The master server rate limits its clients, so lowering this below
+    /// the default of 6 seconds may get the client throttled.
+    ///
+    /// # Arguments
+    /// * `delay` - The time to wait before requesting the next page
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay;
+    }
+
+    /// Set whether the delay backs off after empty responses
+    ///
+    /// A response without any server nor end of list may mean the master
+    /// server is throttling the client. When on, the delay before the next
+    /// request doubles after each such response in a row (`delay * 2^n`),
+    /// up to 60 seconds, and goes back to the normal delay (see
+    /// [`set_delay`](#method.set_delay)) once a page with servers arrives.
+    /// Off by default.
+    ///
+    /// A query gives up with [`MsqError::EmptyResponses`] after 4 empty
+    /// responses in a row. When on, only the responses to requests sent
+    /// with the delay at its cap count towards that limit, so the backoff
+    /// always reaches 60 seconds first. EX: with the default 6 seconds
+    /// delay, the requests after 1 to 4 empty responses wait 12, 24, 48
+    /// then 60 seconds, and the query gives up on the 8th empty response.
+    /// A zero delay never backs off, the limit applies as when off.
+    ///
+    /// # Arguments
+    /// * `on` - `true` to back off after empty responses
+    pub fn set_adaptive_delay(&mut self, on: bool) {
+        self.adaptive_delay = on;
+    }
+
+    /// Set whether the delay is skipped after a full page
+    ///
+    /// Heuristic: a response holding as many servers as the master server
+    /// ever sends at once (231, the most observed in a single packet) means
+    /// the list goes on and the next page is likely ready right away, so
+    /// it is requested without waiting. After a partial page, the normal
+    /// delay applies (see [`set_delay`](#method.set_delay)). Off by default.
+    ///
+    /// **NOTE**: Broad queries are mostly full pages, so this sends requests
+    /// back to back for most of the list and makes throttling by the
+    /// master server more likely.
+    ///
+    /// # Arguments
+    /// * `on` - `true` to request the page following a full page right away
+    pub fn set_skip_delay_when_full(&mut self, on: bool) {
+        self.skip_delay_when_full = on;
+    }
+
+    /// Set the size of the socket's receive buffer (`SO_RCVBUF`)
+    ///
+    /// A larger buffer avoids the kernel dropping packets while the client
+    /// is busy between pages.
+    ///
+    /// **NOTE**: The OS may adjust the value: Linux doubles it and caps it at
+    /// `net.core.rmem_max`, other platforms have their own limits.
+    ///
+    /// # Arguments
+    /// * `bytes` - Requested receive buffer size in bytes
+    pub fn set_recv_buffer(&mut self, bytes: usize) -> Result<()> {
+        SockRef::from(&self.sock).set_recv_buffer_size(bytes)?;
+        self.recv_buffer = Some(bytes);
+        Ok(())
+    }
+
+    /// Set the time-to-live (`IP_TTL`) of the packets sent to the master server
+    ///
+    /// # Arguments
+    /// * `ttl` - Time-to-live, between `1` and `255` on most platforms
+    pub fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.sock.set_ttl(ttl)?;
+        self.ttl = Some(ttl);
+        Ok(())
+    }
+
+    /// Set whether packets with an unknown header are discarded instead of failing the query
+    ///
+    /// A stray packet (EX: a late response to a previous query) makes the
+    /// query fail with [`MsqError::MismatchedHeader`] (or
+    /// [`MsqError::UnexpectedResponse`]) by default. When
+    /// skipping, such packets are dropped and the client keeps waiting for
+    /// a valid response, still bounded by the response timeout (see
+    /// [`set_timeout`](#method.set_timeout)) which is not restarted.
+    ///
+    /// # Arguments
+    /// * `skip` - `true` to discard packets with an unknown header
+    pub fn set_skip_unknown_packets(&mut self, skip: bool) {
+        self.skip_unknown_packets = skip;
+    }
+
+    /// Set the longest a whole query may run, across all of its pages
+    ///
+    /// Unlike the response timeouts (see [`set_timeout`](#method.set_timeout)),
+    /// this bounds the entire enumeration, delays between pages included.
+    /// Once exceeded, the query stops with [`MsqError::Timeout`]; servers
+    /// already sent through the channel are kept by the receiver. Each appid
+    /// of [`query_appids`](#method.query_appids) is its own query. No limit by default.
+    ///
+    /// # Arguments
+    /// * `max` - The time budget of a query, `None` for no limit
+    pub fn set_deadline(&mut self, max: Option<Duration>) {
+        self.deadline = max;
+    }
+
+    /// Set the most pages (response packets) a query may request
+    ///
+    /// Once that many pages were received, the query stops as if the master
+    /// server ended the list, even though more servers may be left. This caps
+    /// the number of round trips, bounding both the query's duration and the
+    /// load put on the master server. No limit by default.
+    ///
+    /// # Arguments
+    /// * `max` - The most pages to request, `None` for no limit
+    pub fn set_max_pages(&mut self, max: Option<usize>) {
+        self.max_pages = max;
+    }
+
+    /// Set the most ports of a single IP address a query may return
+    ///
+    /// A host running many servers (sometimes thousands) can dominate the
+    /// results. Past `max` distinct ports of the same address, its other
+    /// servers are dropped from the query's results, for a more
+    /// representative sample. No limit by default.
+    ///
+    /// **NOTE**: This is client-side filtering, the master server still
+    /// sends every server and the query takes as many pages as without it.
+    ///
+    /// # Arguments
+    /// * `max` - The most ports per address, `None` for no limit
+    pub fn set_max_ports_per_ip(&mut self, max: Option<usize>) {
+        self.max_ports_per_ip = max;
+    }
+
+    /// Set a token to cancel the running (and following) queries
+    ///
+    /// Once the token is cancelled, a query waiting for a response or for the
+    /// delay between pages stops right away with [`MsqError::Cancelled`].
+    ///
+    /// # Arguments
+    /// * `token` - [`CancellationToken`] from `tokio-util`, `None` to remove it
+    pub fn set_cancel_token(&mut self, token: Option<CancellationToken>) {
+        self.cancel = token;
+    }
+
+    /// Set whether queries asking for every server of the platform are refused
+    ///
+    /// An empty filter with `Region::All` enumerates every server known to
+    /// the master server: a huge, slow query likely to get the client
+    /// throttled, usually launched by mistake (EX: a filter left empty by
+    /// a missing config entry). With strict mode on, such a query fails
+    /// with [`MsqError::UnfilteredQuery`] before anything is sent. Off by
+    /// default, turn it back off to run a full enumeration on purpose.
+    ///
+    /// # Arguments
+    /// * `on` - `true` to refuse unfiltered queries of every region
+    pub fn set_strict(&mut self, on: bool) {
+        self.strict = on;
+    }
+
+    /// Set whether filters are checked before sending
+    ///
+    /// A malformed filter (EX: a special filter never closed, see
+    /// [`Filter::is_valid`]) is not rejected by the master server, the query
+    /// just finds no server. With validation on (the default), such a query
+    /// fails with [`MsqError::MalformedFilter`] before anything is sent.
+    /// Raw filters (EX: [`query_raw`](#method.query_raw)) must read back as
+    /// `\key\value` tokens. Filters too long still fail with
+    /// [`MsqError::RequestTooLarge`]. Turn it off to send anything as-is.
+    ///
+    /// # Arguments
+    /// * `on` - `true` to check every filter before sending
+    pub fn set_validate(&mut self, on: bool) {
+        self.validate = on;
+    }
+
+    /// Set how [`query_appids`](#method.query_appids) remembers the servers already sent
+    ///
+    /// [`DedupMode::Exact`] by default, which keeps every server seen in
+    /// memory for the whole sweep. For huge sweeps on constrained hosts,
+    /// [`DedupMode::Approximate`] bounds that memory (see
+    /// [`DedupMode::memory_bound`]) at the cost of dropping a small share of
+    /// unique servers wrongly taken for repeats.
+    ///
+    /// # Arguments
+    /// * `mode` - The dedup mode of the following sweeps
+    pub fn set_dedup_mode(&mut self, mode: DedupMode) {
+        self.dedup_mode = mode;
+    }
+
+    /// Set the bytes every response from the master server starts with
+    ///
+    /// Defaults to Valve's `0xFF 0xFF 0xFF 0xFF 0x66 0x0A`. Community
+    /// reimplementations of the master server may answer with a different
+    /// magic, set theirs to query them. Responses not starting with the
+    /// configured header still fail with [`MsqError::MismatchedHeader`], or
+    /// [`MsqError::UnexpectedResponse`] when only the type byte following
+    /// `0xFF 0xFF 0xFF 0xFF` differs (or are skipped, see
+    /// [`set_skip_unknown_packets`](#method.set_skip_unknown_packets)).
+    ///
+    /// # Arguments
+    /// * `header` - The expected response header
+    pub fn set_response_header(&mut self, header: Vec<u8>) {
+        self.response_header = header;
+    }
+
+    /// Set a channel to receive a [`Progress`] snapshot after every page of a query
+    ///
+    /// Snapshots are dropped rather than slowing the query down when the
+    /// channel is full.
+    ///
+    /// # Arguments
+    /// * `sender` - Channel which receives the progress, `None` to remove it
+    pub fn set_progress_sender(&mut self, sender: Option<Sender<Progress>>) {
+        self.progress = sender;
+    }
+
+    /// Set a channel to receive a [`QueryComplete`] when a query reaches the end of the list
+    ///
+    /// The servers channel closes both when the list is complete and when the
+    /// query stops early, this tells the two apart: one message is sent per
+    /// query, right after the master server's end of list marker (`0.0.0.0:0`)
+    /// is received and before the servers channel closes. Nothing is sent for
+    /// a query stopped early (error, cancellation,
+    /// [`set_max_pages`](#method.set_max_pages)).
+    ///
+    /// # Arguments
+    /// * `sender` - Channel which receives the completion, `None` to remove it
+    pub fn set_completion_sender(&mut self, sender: Option<Sender<QueryComplete>>) {
+        self.completion = sender;
+    }
+
+    /// Set how long to wait for every response from the master server
+    ///
+    /// Same as calling both [`set_initial_timeout`](#method.set_initial_timeout)
+    /// and [`set_page_timeout`](#method.set_page_timeout). When a response takes
+    /// longer, the query fails with [`MsqError::Timeout`]. Waits forever by default.
+    ///
+    /// # Arguments
+    /// * `timeout` - The time to wait for a response, `None` to wait forever
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.initial_timeout = timeout;
+        self.page_timeout = timeout;
+    }
+
+    /// Set how long to wait for the first response of a query
+    ///
+    /// The master server usually takes longer to answer the first request
+    /// (`0.0.0.0:0` seed) than the following pages.
+    ///
+    /// # Arguments
+    /// * `timeout` - The time to wait for the first response, `None` to wait forever
+    pub fn set_initial_timeout(&mut self, timeout: Option<Duration>) {
+        self.initial_timeout = timeout;
+    }
+
+    /// Set how long to wait for each response after the first one of a query
+    ///
+    /// # Arguments
+    /// * `timeout` - The time to wait for each following page, `None` to wait forever
+    pub fn set_page_timeout(&mut self, timeout: Option<Duration>) {
+        self.page_timeout = timeout;
+    }
+
+    /// Set how long a query waits for room in a full channel
+    ///
+    /// Applies to the servers sent one by one by [`query`](#method.query),
+    /// [`run`](#method.run), [`query_raw`](#method.query_raw),
+    /// [`query_raw_bytes`](#method.query_raw_bytes) and
+    /// [`query_appids`](#method.query_appids). While the channel is full, the
+    /// following pages are held (4 at most) then the query pauses, and the
+    /// master server may forget the seed of a query paused for too long.
+    /// When a server waits longer than this, the query fails with
+    /// [`MsqError::ChannelStalled`]. 30 seconds by default.
+    ///
+    /// # Arguments
+    /// * `timeout` - The time to wait for room in the channel, `None` to wait forever
+    pub fn set_send_timeout(&mut self, timeout: Option<Duration>) {
+        self.send_timeout = timeout;
+    }
+
+    /// Set whether every query re-resolves the master server and rotates to
+    /// its next address
+    ///
+    /// `hl2master.steampowered.com` resolves to several hosts, and the one
+    /// picked by [`connect`](#method.connect) may be slow or throttled. With
+    /// rotation on, each query resolves the hostname given to `connect` again
+    /// and connects to the next address of the list, spreading the load.
+    /// Off by default.
+    ///
+    /// **NOTE**: This costs a DNS lookup (through the OS resolver and its
+    /// cache) before every query.
+    ///
+    /// # Arguments
+    /// * `on` - `true` to rotate across the master server's addresses
+    pub fn set_rotate_master(&mut self, on: bool) {
+        self.rotate_master = on;
+    }
+
+    /// Set the function resolving the master server's hostname
+    ///
+    /// Replaces the OS resolver used by [`connect`](#method.connect),
+    /// [`set_rotate_master`](#method.set_rotate_master) and
+    /// `connect_via_proxy` (feature `socks5`), for example to use a
+    /// custom DNS client or a fixed list of addresses. Set it before
+    /// connecting.
+    ///
+    /// # Arguments
+    /// * `resolver` - Maps the address given to `connect` to its socket addresses
+    pub fn set_resolver<F>(&mut self, resolver: F)
+    where
+        F: Fn(&str) -> Result<Vec<SocketAddr>> + Send + Sync + 'static,
+    {
+        self.resolver = Some(Box::new(resolver));
+    }
+
+    /// Set the function resolving the hostname of each server for
+    /// [`query_with_rdns`](#method.query_with_rdns)
+    ///
+    /// * Requires feature: `rdns`
+    /// * Replaces the OS resolver (PTR lookup), for example to use a custom
+    ///   DNS client. Called from a blocking thread, `None` when not found.
+    ///
+    /// # Arguments
+    /// * `resolver` - Maps a server's address to its hostname
+    #[cfg(feature = "rdns")]
+    pub fn set_reverse_resolver<F>(&mut self, resolver: F)
+    where
+        F: Fn(&Address) -> Option<String> + Send + Sync + 'static,
+    {
+        self.reverse_resolver = Some(Arc::new(resolver));
+    }
+
+    /// Set a file recording every request sent and packet received, with their timing
+    ///
+    /// * Requires feature: `replay`
+    /// * The file is created (or truncated) right away and records every
+    ///   following query, until set to `None`. See [`replay`](crate::replay)
+    ///   to parse a recording again without the network, EX: to turn an odd
+    ///   response from the master server into a regression test.
+    ///
+    /// Each datagram is one line: `>` (sent) or `<` (received), the
+    /// milliseconds since the recording started, then the payload in hex
+    /// (EX: `< 52 ffffffff660a010101016987000000000000`).
+    ///
+    /// **NOTE**: Lines are written as the packets go, without buffering, so
+    /// this slows queries down slightly. Meant for debugging.
+    ///
+    /// # Arguments
+    /// * `path` - The log file to write, `None` to stop recording
+    #[cfg(feature = "replay")]
+    pub fn set_record_path(&mut self, path: Option<impl AsRef<Path>>) -> Result<()> {
+        self.recorder = match path {
+            Some(path) => Some(Recorder::create(path.as_ref())?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Connect the client to the given master server address/hostname
+    ///
+    /// Nothing assumes the Steam master's port: mirrors and other games'
+    /// masters listening on any port are queried the same way.
+    ///
+    /// The hostname is resolved (with the resolver set by
+    /// [`set_resolver`](#method.set_resolver), if any) and only the addresses
+    /// of the socket's IP family are kept, in the order resolved: an IPv4
+    /// socket (the default, bound to `0.0.0.0`) connects to the first IPv4
+    /// address, even when the hostname resolves to IPv6 addresses first.
+    /// Bind an IPv6 socket (see [`from_socket`](#method.from_socket)) to
+    /// reach an IPv6 master server. Fails when no address of the socket's
+    /// family is found.
+    ///
+    /// # Arguments
+    /// * `master_server_addr` - The master server's hostname/ip address and port
+    ///   (EX: `hl2master.steampowered.com:27011`)
+    ///
+    /// # Example
+    /// ```no_run
+    /// use msq::MSQClient;
     /// use std::io::Result;
     ///
     /// #[tokio::main]
@@ -83,104 +866,1397 @@ impl MSQClient {
     /// }
     /// ```
     pub async fn connect(&mut self, master_server_addr: &str) -> Result<()> {
-        self.sock.connect(master_server_addr).await?;
+        let addrs = self.resolve(master_server_addr).await?;
+        self.sock.connect(addrs[0]).await?;
+        self.master_addr = Some(String::from(master_server_addr));
+        #[cfg(feature = "socks5")]
+        {
+            self.proxy = None;
+        }
+        Ok(())
+    }
+
+    /// Connect the client to the given master server through a SOCKS5 proxy
+    ///
+    /// * Requires feature: `socks5`
+    /// * Opens a TCP connection to the proxy and asks it to relay UDP
+    ///   (`UDP ASSOCIATE`, RFC 1928), then every request and response goes
+    ///   through the relay. Calling [`connect`](#method.connect) goes back
+    ///   to querying directly.
+    ///
+    /// **NOTE**: SOCKS5 UDP support is limited:
+    /// * Only proxies without authentication are supported.
+    /// * The master server's hostname is resolved locally, like for
+    ///   [`connect`](#method.connect) (see [`set_resolver`](#method.set_resolver)),
+    ///   to its first IPv4 address. Master rotation (see
+    ///   [`set_rotate_master`](#method.set_rotate_master)) is not applied.
+    /// * Many proxies (SSH `-D`, Tor) only relay TCP and refuse the
+    ///   association with [`MsqError::ProxyRefused`].
+    /// * The association ends with the TCP connection, when the proxy
+    ///   closes it [`reconnect`](#method.reconnect) sets it up again.
+    ///
+    /// # Arguments
+    /// * `proxy_addr` - The SOCKS5 proxy's hostname/ip address and port (EX: `127.0.0.1:1080`)
+    /// * `master_server_addr` - The master server's hostname/ip address and port
+    ///   (EX: `hl2master.steampowered.com:27011`)
+    #[cfg(feature = "socks5")]
+    pub async fn connect_via_proxy(
+        &mut self,
+        proxy_addr: &str,
+        master_server_addr: &str,
+    ) -> Result<()> {
+        let target = self
+            .resolve(master_server_addr)
+            .await?
+            .into_iter()
+            .find_map(|addr| match addr {
+                SocketAddr::V4(addr) => Some(addr),
+                SocketAddr::V6(_) => None,
+            })
+            .ok_or(MsqError::InvalidAddress)?;
+        let relay = Socks5Relay::associate(proxy_addr, target).await?;
+        self.sock.connect(relay.relay).await?;
+        self.master_addr = Some(String::from(master_server_addr));
+        self.proxy = Some(relay);
         Ok(())
     }
 
+    /// Rebind a fresh UDP socket and connect it to the last master server
+    ///
+    /// Recovers the client after an I/O error left the socket in a bad
+    /// state. The client's settings and socket options are kept, and the
+    /// new socket is bound to the same local IP address (EX: IPv6, or a
+    /// specific interface given to [`from_socket`](#method.from_socket)).
+    /// Fails with [`MsqError::NotConnected`] if [`connect`](#method.connect)
+    /// was never called.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let master_server_addr = self.master_addr.clone().ok_or(MsqError::NotConnected)?;
+        let local_ip = self.sock.local_addr()?.ip();
+        self.sock = UdpSocket::bind(SocketAddr::new(local_ip, 0)).await?;
+        if let Some(bytes) = self.recv_buffer {
+            self.set_recv_buffer(bytes)?;
+        }
+        if let Some(ttl) = self.ttl {
+            self.set_ttl(ttl)?;
+        }
+        #[cfg(feature = "socks5")]
+        if let Some(proxy_addr) = self.proxy.take().map(|relay| relay.proxy_addr) {
+            return self
+                .connect_via_proxy(&proxy_addr, &master_server_addr)
+                .await;
+        }
+        self.connect(&master_server_addr).await
+    }
+
+    /// Check that the master server answers, returning the round-trip time
+    ///
+    /// Sends a single first page request for the region and filter set with
+    /// [`set_region`](#method.set_region) and [`set_filter`](#method.set_filter)
+    /// (`Region::All` and an empty filter by default) and waits for the first
+    /// page, without paging through the rest of the list. The request goes
+    /// through the same checks as a query (strict mode, filter validation)
+    /// and a challenge is answered like for a query, its round trip counting
+    /// in the returned time. Bounded by the initial timeout (see
+    /// [`set_initial_timeout`](#method.set_initial_timeout)).
+    ///
+    /// **NOTE**: This is a real request, it counts against the master
+    /// server's rate limit just like the first page of a query. In strict
+    /// mode (see [`set_strict`](#method.set_strict)), set a region or a
+    /// filter first or it fails with [`MsqError::UnfilteredQuery`].
+    pub async fn ping(&mut self) -> Result<Duration> {
+        let mut buf: [u8; 2048] = [0x00; 2048];
+        let region_code = self.region.as_u8();
+        let filter_str = self.filter_string(&self.filter)?;
+        let filter_bytes = self.request_filter(region_code, filter_str.as_bytes(), false)?;
+        let started = Instant::now();
+        self.exchange(
+            &mut buf,
+            region_code,
+            &filter_bytes,
+            &mut None,
+            (Address::default(), 0),
+            self.initial_timeout,
+        )
+        .await?;
+        Ok(started.elapsed())
+    }
+
     /// Query with raw bytes
     ///
+    /// The filter is borrowed, an owned `String` or a `&str` can be passed
+    /// as-is.
+    ///
     /// # Arguments
     /// * `region_code` - Region code in u8 (`0x00 - 0x07 / 0xFF`)
     /// * `filter_str` - Filter in plain string (EX: `\\appid\\240\\map\\de_dust2`)
+    /// * `sender` - Channel which receives each server found
     pub async fn query_raw(
         &mut self,
         region_code: u8,
-        filter_str: &str,
-        sender: Sender<(Address, u16)>,
-    ) -> Result<()> {
-        self.send(region_code, filter_str, EMPTY_ADRESS, 0).await?; // First Packet
-        self.recv(region_code, filter_str, sender).await
+        filter_str: impl AsRef<str>,
+        sender: Sender<Result<(Address, u16)>>,
+    ) -> Result<QueryOutcome> {
+        let mut filter: Cursor<Vec<u8>> = Cursor::new(Vec::default());
+        filter.write_cstring(filter_str.as_ref())?;
+        self.query_raw_bytes(region_code, filter.get_ref(), sender)
+            .await
     }
 
-    /// Query with specified Region and Filter
+    /// Query with a pre-built filter byte buffer
     ///
-    /// Returns a Vec list of IP addresses in strings
+    /// The bytes are written to the request packet as-is, without any UTF-8
+    /// assumption, which allows replaying captured requests exactly.
+    /// The caller owns the correctness of the bytes: they must already be a
+    /// valid filter. The `0x00` terminator is optional and added when missing,
+    /// but any other `0x00` is rejected before sending since it would cut the
+    /// filter short.
     ///
     /// # Arguments
-    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
-    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
-    pub async fn query(
+    /// * `region_code` - Region code in u8 (`0x00 - 0x07 / 0xFF`)
+    /// * `filter_bytes` - Null-terminated filter (EX: `b"\\appid\\240\0"`)
+    /// * `sender` - Channel which receives each server found
+    pub async fn query_raw_bytes(
         &mut self,
-        region: Region,
-        filter: Filter,
-        sender: Sender<(Address, u16)>,
-    ) -> Result<()> {
-        self.query_raw(region.as_u8(), &filter.as_string(), sender)
+        region_code: u8,
+        filter_bytes: &[u8],
+        sender: Sender<Result<(Address, u16)>>,
+    ) -> Result<QueryOutcome> {
+        self.query_forwarded(region_code, filter_bytes, true, sender)
             .await
     }
 
-    async fn send(
+    // `query_raw_bytes`, with the filter checked as raw bytes or not (a
+    // `Filter` is checked beforehand, see `filter_string`)
+    async fn query_forwarded(
+        &mut self,
+        region_code: u8,
+        filter_bytes: &[u8],
+        raw: bool,
+        sender: Sender<Result<(Address, u16)>>,
+    ) -> Result<QueryOutcome> {
+        let (batch_sender, mut batch_receiver) = channel(FORWARD_PAGES);
+        let send_timeout = self.send_timeout;
+        let query = self.query_paged(region_code, filter_bytes, raw, batch_sender);
+        // Pages wait in the batch channel while `sender` is full, the query
+        // pauses once it is full too. Returning early drops the batch
+        // receiver, which stops the query at its next page.
+        let forward = async move {
+            loop {
+                let batch = tokio::select! {
+                    _ = sender.closed() => return Ok(None),
+                    batch = batch_receiver.recv() => batch,
+                };
+                let Some(batch) = batch else {
+                    break;
+                };
+                for server in batch {
+                    if !send_within(&sender, Ok(server), send_timeout).await? {
+                        return Ok(None);
+                    }
+                }
+            }
+            Ok(Some(sender))
+        };
+
+        match tokio::join!(query, forward) {
+            (_, Err(err)) => Err(err),
+            (Err(err), Ok(Some(sender))) => {
+                let _ = send_within(&sender, Err(duplicate(&err)), send_timeout).await;
+                Err(err)
+            }
+            (result, Ok(_)) => result,
+        }
+    }
+
+    /// Query with a pre-built filter byte buffer, sending the servers of each page at once
+    ///
+    /// See [`query_raw_bytes`](#method.query_raw_bytes) and
+    /// [`query_batched`](#method.query_batched).
+    ///
+    /// # Arguments
+    /// * `region_code` - Region code in u8 (`0x00 - 0x07 / 0xFF`)
+    /// * `filter_bytes` - Null-terminated filter (EX: `b"\\appid\\240\0"`)
+    /// * `sender` - Channel which receives the servers of each page
+    pub async fn query_raw_batched(
+        &mut self,
+        region_code: u8,
+        filter_bytes: &[u8],
+        sender: Sender<Vec<(Address, u16)>>,
+    ) -> Result<QueryOutcome> {
+        self.query_paged(region_code, filter_bytes, true, sender)
+            .await
+    }
+
+    // `query_raw_batched`, with the filter checked as raw bytes or not
+    async fn query_paged(
+        &mut self,
+        region_code: u8,
+        filter_bytes: &[u8],
+        raw: bool,
+        sender: Sender<Vec<(Address, u16)>>,
+    ) -> Result<QueryOutcome> {
+        self.start_paging(region_code, filter_bytes, raw)?;
+        Self::within_deadline(self.deadline, self.recv(sender)).await
+    }
+
+    /// Query with raw bytes, collecting every response packet verbatim
+    ///
+    /// Pages through the whole list like [`query_raw`](#method.query_raw),
+    /// but returns the payload of each datagram from the master server
+    /// unparsed (header and end of list marker included), in the order they
+    /// were received. Useful to archive responses and reprocess them later.
+    ///
+    /// **NOTE**: Every packet is kept in memory until the query completes,
+    /// up to ~1.4 KB per page of 231 servers, so broad queries spanning
+    /// thousands of pages hold several megabytes.
+    ///
+    /// # Arguments
+    /// * `region_code` - Region code in u8 (`0x00 - 0x07 / 0xFF`)
+    /// * `filter_str` - Filter in plain string (EX: `\\appid\\240\\map\\de_dust2`)
+    pub async fn query_raw_packets(
         &mut self,
         region_code: u8,
         filter_str: &str,
+    ) -> Result<Vec<Vec<u8>>> {
+        self.start_query_raw(region_code, filter_str.as_bytes())?;
+        Self::within_deadline(self.deadline, self.recv_packets()).await
+    }
+
+    /// Set the region queried by [`run`](#method.run) (`Region::All` by default)
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    /// Set the filter queried by [`run`](#method.run) (empty by default)
+    ///
+    /// # Arguments
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    /// Query with the Region and Filter set on the client
+    ///
+    /// Same as [`query`](#method.query) with the values of
+    /// [`set_region`](#method.set_region) and [`set_filter`](#method.set_filter),
+    /// for services refreshing the same query over and over.
+    ///
+    /// # Arguments
+    /// * `sender` - Channel which receives each server found
+    ///
+    /// # Example
+    /// ```no_run
+    /// use msq::{MSQClient, Region, Filter};
+    /// use std::time::Duration;
+    /// use tokio::sync::mpsc;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> msq::Result<()> {
+    ///     let mut client = MSQClient::new().await?;
+    ///     client.connect("hl2master.steampowered.com:27011").await?;
+    ///     client.set_region(Region::Europe);
+    ///     client.set_filter(Filter::new().appid(240));
+    ///
+    ///     loop {
+    ///         let (sender, mut receiver) = mpsc::channel(64);
+    ///         tokio::spawn(async move {
+    ///             while let Some(Ok((address, port))) = receiver.recv().await {
+    ///                 // Do something with each server
+    ///             }
+    ///         });
+    ///         client.run(sender).await?;
+    ///         tokio::time::sleep(Duration::from_secs(300)).await;
+    ///     }
+    /// }
+    /// ```
+    pub async fn run(&mut self, sender: Sender<Result<(Address, u16)>>) -> Result<QueryOutcome> {
+        let filter_str = self.filter_string(&self.filter)?;
+        self.query_forwarded(self.region.as_u8(), filter_str.as_bytes(), false, sender)
+            .await
+    }
+
+    /// Start a query with specified Region and Filter, stepped through with
+    /// [`poll_next_page`](#method.poll_next_page)
+    ///
+    /// Nothing is sent until the first poll. Starting a new query drops the
+    /// one in progress.
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    pub fn start_query(&mut self, region: Region, filter: Filter) -> Result<()> {
+        let filter_str = self.filter_string(&filter)?;
+        self.start_paging(region.as_u8(), filter_str.as_bytes(), false)
+    }
+
+    /// Start a query with a pre-built filter byte buffer, see
+    /// [`start_query`](#method.start_query) and [`query_raw_bytes`](#method.query_raw_bytes)
+    ///
+    /// # Arguments
+    /// * `region_code` - Region code in u8 (`0x00 - 0x07 / 0xFF`)
+    /// * `filter_bytes` - Null-terminated filter (EX: `b"\\appid\\240\0"`)
+    pub fn start_query_raw(&mut self, region_code: u8, filter_bytes: &[u8]) -> Result<()> {
+        self.start_paging(region_code, filter_bytes, true)
+    }
+
+    // `start_query_raw`, with the filter checked as raw bytes or not
+    fn start_paging(&mut self, region_code: u8, filter_bytes: &[u8], raw: bool) -> Result<()> {
+        let filter_bytes = self.request_filter(region_code, filter_bytes, raw)?;
+        self.paging = Some(Paging {
+            region_code,
+            filter_bytes,
+            seed: Seed::default(),
+            challenge: None,
+            ports: HashMap::new(),
+            last_full: false,
+            pages: 0,
+            servers: 0,
+            end_of_list: false,
+        });
+        Ok(())
+    }
+
+    /// Request the next page of the started query and wait for it
+    ///
+    /// Returns the servers of the page (empty for a keepalive page), or
+    /// `None` once the master server ended the list or when no query was
+    /// started. Drives the enumeration one round trip at a time, so it can
+    /// be used inside the caller's own `tokio::select!` loop. Every page but
+    /// the first waits the delay before its request (see
+    /// [`set_delay`](#method.set_delay)). After an I/O error or a timeout,
+    /// polling again requests the same page.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use msq::{MSQClient, Region, Filter};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> msq::Result<()> {
+    ///     let mut client = MSQClient::new().await?;
+    ///     client.connect("hl2master.steampowered.com:27011").await?;
+    ///
+    ///     client.start_query(Region::Europe, Filter::new().appid(240))?;
+    ///     while let Some(servers) = client.poll_next_page().await? {
+    ///         // Do something with each page, or other work in between
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn poll_next_page(&mut self) -> Result<Option<Vec<(Address, u16)>>> {
+        let mut buf: [u8; 2048] = [0x00; 2048];
+        Ok(self
+            .poll_page(&mut buf)
+            .await?
+            .map(|(_, page)| page.servers))
+    }
+
+    /// Query with specified Region and Filter
+    ///
+    /// Sends each server found through `sender` and returns how the query
+    /// finished, see [`QueryOutcome`] (a region/filter matching nothing is
+    /// [`QueryOutcome::Empty`], not an error)
+    ///
+    /// Every server is sent as `Ok`. When the query fails, the error is also
+    /// sent as the last message before the channel closes, so a consumer
+    /// draining the channel knows the list is incomplete. A channel closing
+    /// without any `Err` means the whole list was received. Same for every
+    /// method sending single servers through a channel.
+    ///
+    /// **NOTE**: Every server is in the channel when the query returns. While
+    /// the channel is full, the client holds up to 4 pages then pauses the
+    /// query, which fails with [`MsqError::ChannelStalled`] when the channel
+    /// stays full too long (see [`set_send_timeout`](#method.set_send_timeout)).
+    /// The minimum safe capacity depends on when the channel is read:
+    /// * Read while the query runs (EX: from a spawned task): any capacity,
+    ///   the smallest one Tokio allows (`1`, `channel(0)` panics) included.
+    ///   `64` is a good default.
+    /// * Read only once the query returned: the whole list and the error
+    ///   must fit, or the query stalls.
+    ///
+    /// Once the receiver is dropped, the query stops requesting pages.
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    pub async fn query(
+        &mut self,
+        region: Region,
+        filter: Filter,
+        sender: Sender<Result<(Address, u16)>>,
+    ) -> Result<QueryOutcome> {
+        let filter_str = match self.filter_string(&filter) {
+            Ok(filter_str) => filter_str,
+            Err(err) => {
+                let _ = sender.send(Err(duplicate(&err))).await;
+                return Err(err);
+            }
+        };
+        self.query_forwarded(region.as_u8(), filter_str.as_bytes(), false, sender)
+            .await
+    }
+
+    /// Query with specified Region and Filter, configured by [`QueryOpts`] for this query only
+    ///
+    /// Sends each server found through `sender` like [`query`](#method.query)
+    /// and returns the [`QueryStats`] of the query. The options replace the
+    /// client's delay and timeouts for the query, which are set back once
+    /// it ends. A page timing out is requested again while `opts.retries`
+    /// allows, the other settings of the client (deadline, cancellation,
+    /// page limit, ...) still apply.
+    ///
+    /// **NOTE**: Each server is sent before the next page is requested, so
+    /// a full channel delays the query, unlike [`query`](#method.query)
+    /// (see [`query_batched`](#method.query_batched)).
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    /// * `opts` - [`QueryOpts`] of the query (EX: `QueryOpts::default()`)
+    /// * `sender` - Channel which receives each server found
+    ///
+    /// # Example
+    /// ```no_run
+    /// use msq::{MSQClient, QueryOpts, Region, Filter};
+    /// use std::time::Duration;
+    /// use tokio::sync::mpsc::channel;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> msq::Result<()> {
+    ///     let mut client = MSQClient::new().await?;
+    ///     client.connect("hl2master.steampowered.com:27011").await?;
+    ///
+    ///     let opts = QueryOpts {
+    ///         timeout: Some(Duration::from_secs(5)),
+    ///         retries: 3,
+    ///         max_results: Some(1000),
+    ///         ..QueryOpts::default()
+    ///     };
+    ///     let (sender, mut receiver) = channel(64);
+    ///     let query = client.query_with(Region::Europe, Filter::new().appid(240), opts, sender);
+    ///     let print = async {
+    ///         while let Some(Ok(server)) = receiver.recv().await {
+    ///             println!("{:?}", server);
+    ///         }
+    ///     };
+    ///
+    ///     let (stats, _) = tokio::join!(query, print);
+    ///     let stats = stats?;
+    ///     println!("{} servers in {:?}", stats.servers, stats.elapsed);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_with(
+        &mut self,
+        region: Region,
+        filter: Filter,
+        opts: QueryOpts,
+        sender: Sender<Result<(Address, u16)>>,
+    ) -> Result<QueryStats> {
+        let saved = (self.delay, self.initial_timeout, self.page_timeout);
+        (self.delay, self.initial_timeout, self.page_timeout) =
+            (opts.delay, opts.timeout, opts.timeout);
+
+        let result = match self.start_query(region, filter) {
+            Ok(()) => Self::within_deadline(self.deadline, self.recv_with(&opts, &sender)).await,
+            Err(err) => Err(err),
+        };
+        (self.delay, self.initial_timeout, self.page_timeout) = saved;
+
+        if let Err(err) = &result {
+            // The consumer may have stopped reading already
+            let _ = sender.send(Err(duplicate(err))).await;
+        }
+        result
+    }
+
+    /// Query with specified Region and Filter, sending the servers of each page at once
+    ///
+    /// Sends one `Vec` per response packet from the master server (up to 231
+    /// servers), following its page boundaries, instead of one message per
+    /// server like [`query`](#method.query). Suited to bulk consumers such
+    /// as database inserts. Pages without any server are not sent. Once the
+    /// receiver is dropped, the query stops and returns the servers sent.
+    ///
+    /// **NOTE**: The next page is requested once the page is sent: a full
+    /// channel delays the query, unlike [`query`](#method.query). Use a
+    /// capacity which holds the whole list (EX: 64 pages, ~15 000 servers)
+    /// when the consumer can't keep up with the delay between requests.
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    /// * `sender` - Channel which receives the servers of each page
+    pub async fn query_batched(
+        &mut self,
+        region: Region,
+        filter: Filter,
+        sender: Sender<Vec<(Address, u16)>>,
+    ) -> Result<QueryOutcome> {
+        let filter_str = self.filter_string(&filter)?;
+        self.query_paged(region.as_u8(), filter_str.as_bytes(), false, sender)
+            .await
+    }
+
+    /// Query with specified Region and Filter, as a [`Stream`] of the servers of each page
+    ///
+    /// Yields one `Vec` per response packet from the master server like
+    /// [`query_batched`](#method.query_batched) (pages without any server
+    /// are skipped), where [`query`](#method.query) sends one message per
+    /// server. Pages compose with stream adapters, EX: one A2S fan-out per
+    /// page with `buffer_unordered`. The client is moved into a spawned
+    /// task which runs the query, so this must be called within a Tokio
+    /// runtime.
+    ///
+    /// When the query fails, the error is the last item. Dropping the
+    /// stream stops the query after the page being requested.
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    pub fn query_page_stream(
+        mut self,
+        region: Region,
+        filter: Filter,
+    ) -> impl Stream<Item = Result<Vec<(Address, u16)>>> {
+        let (sender, receiver) = channel(4);
+        tokio::spawn(async move {
+            let mut buf: [u8; 2048] = [0x00; 2048];
+            let deadline = self.deadline;
+            let pages = async {
+                self.start_query(region, filter)?;
+                while let Some((_, page)) = self.poll_page(&mut buf).await? {
+                    // Nobody left to read the pages
+                    if !page.servers.is_empty() && sender.send(Ok(page.servers)).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(())
+            };
+            if let Err(err) = Self::within_deadline(deadline, pages).await {
+                let _ = sender.send(Err(err)).await;
+            }
+        });
+        PageStream { receiver }
+    }
+
+    /// Query with specified Region and Filter, sending each server found with its hostname
+    ///
+    /// * Requires feature: `rdns`
+    /// * Performs a best-effort reverse DNS (PTR) lookup of every address
+    ///   before sending its servers, `None` when the lookup fails or finds
+    ///   nothing. See [`set_reverse_resolver`](#method.set_reverse_resolver).
+    /// * **NOTE**: Lookups run 16 at a time and each address is only looked up
+    ///   once per query, but a slow resolver can still add seconds per page:
+    ///   servers are sent a page at a time, once every address of the page
+    ///   was looked up.
+    /// * Once the receiver is dropped, the query stops.
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    /// * `sender` - Channel which receives each server found and its hostname
+    #[cfg(feature = "rdns")]
+    pub async fn query_with_rdns(
+        &mut self,
+        region: Region,
+        filter: Filter,
+        sender: Sender<(Address, u16, Option<String>)>,
+    ) -> Result<QueryOutcome> {
+        let resolver: ReverseResolver = match &self.reverse_resolver {
+            Some(resolver) => resolver.clone(),
+            None => Arc::new(crate::rdns::reverse_lookup),
+        };
+
+        let (batch_sender, mut batch_receiver) = channel(4);
+        let query = self.query_batched(region, filter, batch_sender);
+        // Dropping the batch receiver on return stops the query
+        let resolve = async move {
+            let mut names: HashMap<Address, Option<String>> = HashMap::new();
+            while let Some(batch) = batch_receiver.recv().await {
+                let mut pending: Vec<Address> = vec![];
+                for (address, _) in &batch {
+                    if !names.contains_key(address) && !pending.contains(address) {
+                        pending.push(*address);
+                    }
+                }
+
+                for chunk in pending.chunks(RDNS_CONCURRENCY) {
+                    let lookups: Vec<_> = chunk
+                        .iter()
+                        .map(|address| {
+                            let resolver = resolver.clone();
+                            let address = *address;
+                            tokio::task::spawn_blocking(move || resolver(&address))
+                        })
+                        .collect();
+                    for (address, lookup) in chunk.iter().zip(lookups) {
+                        names.insert(*address, lookup.await.ok().flatten());
+                    }
+                }
+
+                for (address, port) in batch {
+                    let name = names[&address].clone();
+                    // Nobody left to read the servers
+                    if sender.send((address, port, name)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        };
+
+        let (result, _) = tokio::join!(query, resolve);
+        result
+    }
+
+    /// Query with specified Region and Filter, writing each server found to `writer`
+    ///
+    /// Each server is written as one `a.b.c.d:port\n` line (EX:
+    /// `192.168.0.1:27015\n`) as its page arrives, without keeping the list
+    /// in memory. The writer is flushed at the end of the query. When writing
+    /// fails, the query still runs to its end and the write error is returned.
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    /// * `writer` - Where to write the servers (EX: a [`tokio::fs::File`])
+    pub async fn query_to_writer(
+        &mut self,
+        region: Region,
+        filter: Filter,
+        mut writer: impl AsyncWrite + Unpin,
+    ) -> Result<QueryOutcome> {
+        let (batch_sender, mut batch_receiver) = channel(4);
+        let query = self.query_batched(region, filter, batch_sender);
+        let write = async {
+            let mut written = Ok(());
+            while let Some(batch) = batch_receiver.recv().await {
+                // Keep draining even after a failed write
+                if written.is_ok() {
+                    let mut lines = String::new();
+                    for (address, port) in batch {
+                        lines += &format!("{}:{}\n", address, port);
+                    }
+                    written = writer.write_all(lines.as_bytes()).await;
+                }
+            }
+            written?;
+            writer.flush().await
+        };
+
+        let (result, written) = tokio::join!(query, write);
+        let outcome = result?;
+        written?;
+        Ok(outcome)
+    }
+
+    /// Query with specified Region and Filter, collecting every server found
+    ///
+    /// A server sent more than once is only listed once, see [`ServerList`].
+    /// The returned [`QueryResult`] also tells whether the master server
+    /// ended the list: an empty list is only a genuine "no server matches"
+    /// when [`terminated`](QueryResult::terminated) is `true`.
+    ///
+    /// **NOTE**: The whole list is kept in memory until the query completes,
+    /// use [`query`](#method.query) to process servers as they arrive.
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    ///
+    /// # Example
+    /// ```no_run
+    /// use msq::{MSQClient, Region, Filter};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> msq::Result<()> {
+    ///     let mut client = MSQClient::new().await?;
+    ///     client.connect("hl2master.steampowered.com:27011").await?;
+    ///
+    ///     let result = client.query_vec(Region::Europe, Filter::new().appid(240)).await?;
+    ///     if !result.terminated {
+    ///         println!("Stopped after {} pages, the list is incomplete", result.pages);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_vec(&mut self, region: Region, filter: Filter) -> Result<QueryResult> {
+        self.start_query(region, filter)?;
+        Self::within_deadline(self.deadline, self.recv_list()).await
+    }
+
+    /// Query with specified Region and Filter, keeping only the servers within a subnet
+    ///
+    /// The master server can only filter on an exact address (`gameaddr`),
+    /// so this enumerates the whole list as [`query_vec`](#method.query_vec)
+    /// does and drops the servers outside of `cidr` as they arrive.
+    ///
+    /// **NOTE**: This is post-filtering, the master server still sends every
+    /// matching server: the query takes as many pages (and as long) as
+    /// without it. Narrow `filter` down to save on requests.
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    /// * `cidr` - IPv4 subnet in CIDR notation (EX: `203.0.113.0/24`)
+    ///
+    /// # Errors
+    /// * [`MsqError::InvalidAddress`] if `cidr` is malformed, before anything is sent
+    pub async fn query_vec_in_subnet(
+        &mut self,
+        region: Region,
+        filter: Filter,
+        cidr: &str,
+    ) -> Result<ServerList> {
+        let (network, mask) = address::parse_cidr(cidr)?;
+        let (sender, mut receiver) = channel::<Result<(Address, u16)>>(64);
+        let mut servers = ServerList::new();
+        let collect = async {
+            // The error is returned by the query itself
+            while let Some(Ok((address, port))) = receiver.recv().await {
+                if address.to_u32() & mask == network {
+                    servers.insert((address, port));
+                }
+            }
+        };
+
+        let (result, _) = tokio::join!(self.query(region, filter, sender), collect);
+        result?;
+        Ok(servers)
+    }
+
+    /// Query with specified Region and Filter, grouping the ports found under each address
+    ///
+    /// Useful to audit hosts running several servers. A port sent more than
+    /// once for the same address is only listed once.
+    ///
+    /// **NOTE**: Like [`query_vec`](#method.query_vec), the whole result is
+    /// kept in memory, which can grow large for broad queries.
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    pub async fn query_grouped(
+        &mut self,
+        region: Region,
+        filter: Filter,
+    ) -> Result<HashMap<Address, Vec<u16>>> {
+        Ok(self.query_vec(region, filter).await?.servers.by_ip())
+    }
+
+    /// Query each given appid separately with the specified Region and Filter
+    ///
+    /// A single broad query gets cut off by the master server before the list
+    /// is complete, so this runs one query per appid (`base_filter` with
+    /// `appid(id)` appended, replacing any appid it already has) and sends
+    /// the union of the results. Servers matched by more than one appid are
+    /// only sent once (see [`set_dedup_mode`](#method.set_dedup_mode)).
+    ///
+    /// **NOTE**: Every appid is a full enumeration of its own, so a sweep of
+    /// N appids takes at least N requests (plus one for every extra page),
+    /// each spaced by the delay (see [`set_delay`](#method.set_delay)).
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `appids` - The appids to query for (EX: `&[240, 440]`)
+    /// * `base_filter` - [`Filter`] builder shared by every appid query
+    /// * `sender` - Channel which receives each server found
+    pub async fn query_appids(
+        &mut self,
+        region: Region,
+        appids: &[u32],
+        base_filter: Filter,
+        sender: Sender<Result<(Address, u16)>>,
+    ) -> Result<QueryOutcome> {
+        let mut seen = Seen::new(self.dedup_mode);
+        let mut count = 0;
+        for (i, appid) in appids.iter().enumerate() {
+            if i > 0 {
+                self.sleep(self.delay).await?;
+            }
+
+            let filter_str = self.filter_string(&base_filter.clone().appid(*appid))?;
+            let (appid_sender, mut appid_receiver) = channel(64);
+            let query =
+                self.query_forwarded(region.as_u8(), filter_str.as_bytes(), false, appid_sender);
+            let forward = async {
+                while let Some(server) = appid_receiver.recv().await {
+                    // Errors are forwarded as-is
+                    if server.as_ref().map_or(true, |server| seen.insert(*server)) {
+                        count += server.is_ok() as usize;
+                        // Keep draining even if the receiver is gone
+                        let _ = sender.send(server).await;
+                    }
+                }
+            };
+
+            let (result, _) = tokio::join!(query, forward);
+            result?;
+        }
+
+        Ok(QueryOutcome::from_count(count))
+    }
+
+    /// Query every region for the given appid, collecting every server found
+    ///
+    /// Runs one query per region (`Region::USEast` to `Region::Africa`, then
+    /// `Region::All` for servers outside of them) and merges the results
+    /// without duplicates. A region timing out is queried again up to 2
+    /// more times, after which the sweep moves on with the next region.
+    /// Uses the client's delay and timeouts, see [`scrape`](crate::scrape)
+    /// for a ready to use version.
+    ///
+    /// **NOTE**: The master server cuts off long lists and rate limits its
+    /// clients, so even a full sweep is not guaranteed to find every server.
+    /// Splitting the query per region only makes each list shorter.
+    ///
+    /// # Arguments
+    /// * `appid` - The appid to query for (EX: `240`)
+    ///
+    /// # Errors
+    /// * [`MsqError::Timeout`] when every region timed out
+    /// * Any other error (socket, cancellation, ...) stops the sweep
+    pub async fn scrape(&mut self, appid: u32) -> Result<ServerList> {
+        let filter_str = Filter::new().appid(appid).as_string();
+        let mut servers = ServerList::new();
+        let mut answered = false;
+
+        for (i, region) in SCRAPE_REGIONS.iter().enumerate() {
+            for attempt in 0..=SCRAPE_RETRIES {
+                if i > 0 || attempt > 0 {
+                    self.sleep(self.delay).await?;
+                }
+
+                let (sender, mut receiver) = channel(64);
+                let query = self.query_raw(region.as_u8(), &filter_str, sender);
+                // Servers received before a timeout are kept
+                let collect = async {
+                    while let Some(Ok(server)) = receiver.recv().await {
+                        servers.insert(server);
+                    }
+                };
+
+                match tokio::join!(query, collect).0 {
+                    Ok(_) => {
+                        answered = true;
+                        break;
+                    }
+                    Err(MsqError::Timeout) => continue,
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        if answered {
+            Ok(servers)
+        } else {
+            Err(MsqError::Timeout)
+        }
+    }
+
+    // Connect to the next address of the master server when rotating
+    async fn rotate(&mut self) -> Result<()> {
+        if !self.rotate_master {
+            return Ok(());
+        }
+        #[cfg(feature = "socks5")]
+        if self.proxy.is_some() {
+            return Ok(());
+        }
+
+        let master_server_addr = self.master_addr.clone().ok_or(MsqError::NotConnected)?;
+        let addrs = self.resolve(&master_server_addr).await?;
+        self.sock
+            .connect(addrs[self.rotation % addrs.len()])
+            .await?;
+        self.rotation = self.rotation.wrapping_add(1);
+        Ok(())
+    }
+
+    // String of the filter, checked first when validating
+    fn filter_string(&self, filter: &Filter) -> Result<String> {
+        if self.validate && !filter.is_well_formed() {
+            return Err(MsqError::MalformedFilter);
+        }
+        Ok(filter.as_string())
+    }
+
+    // Addresses of the master server reachable from the socket (same IP
+    // family), never empty
+    async fn resolve(&self, master_server_addr: &str) -> Result<Vec<SocketAddr>> {
+        let addrs: Vec<SocketAddr> = match &self.resolver {
+            Some(resolver) => resolver(master_server_addr)?,
+            None => lookup_host(master_server_addr).await?.collect(),
+        };
+        let ipv4 = self.sock.local_addr()?.is_ipv4();
+        let addrs: Vec<SocketAddr> = addrs
+            .into_iter()
+            .filter(|addr| addr.is_ipv4() == ipv4)
+            .collect();
+        if addrs.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrNotAvailable,
+                "no master server address of the socket's IP family",
+            )
+            .into());
+        }
+        Ok(addrs)
+    }
+
+    async fn send(
+        &mut self,
+        region_code: u8,
+        filter_bytes: &[u8],
+        challenge: Option<u32>,
         address: Address,
         port: u16,
     ) -> Result<()> {
-        let mut cursor: Cursor<Vec<u8>> = Cursor::new(Vec::default());
-        cursor.write_u8(0x31)?;
-        cursor.write_u8(region_code)?;
-        cursor.write_cstring(&format!(
-            "{}.{}.{}.{}:{}",
-            address.a, address.b, address.c, address.d, port
-        ))?;
-        cursor.write_cstring(filter_str)?;
-        self.sock.send(cursor.get_ref()).await?;
+        let packet = packet::request(region_code, filter_bytes, challenge, &address, port)?;
+        #[cfg(feature = "socks5")]
+        let wrapped = self.proxy.as_ref().map(|relay| relay.wrap(&packet));
+        #[cfg(not(feature = "socks5"))]
+        let wrapped: Option<Vec<u8>> = None;
+        let datagram = wrapped.as_deref().unwrap_or(&packet);
+        packet::check_sent(self.sock.send(datagram).await, datagram.len())?;
+        self.bytes_sent += datagram.len() as u64;
+        #[cfg(feature = "replay")]
+        if let Some(recorder) = &mut self.recorder {
+            recorder.request(&packet)?;
+        }
         Ok(())
     }
 
-    async fn recv(
+    async fn recv_packet(&mut self, buf: &mut [u8], wait_for: Option<Duration>) -> Result<usize> {
+        let recv = async {
+            match wait_for {
+                Some(duration) => match timeout(duration, self.sock.recv(buf)).await {
+                    Ok(len) => Ok(len?),
+                    Err(_) => Err(MsqError::Timeout),
+                },
+                None => Ok(self.sock.recv(buf).await?),
+            }
+        };
+        let len = Self::cancellable(&self.cancel, recv).await??;
+        self.bytes_received += len as u64;
+        #[cfg(feature = "socks5")]
+        let len = match self.proxy {
+            Some(_) => Socks5Relay::unwrap(buf, len),
+            None => len,
+        };
+        #[cfg(feature = "replay")]
+        if let Some(recorder) = &mut self.recorder {
+            recorder.response(&buf[..len])?;
+        }
+        Ok(len)
+    }
+
+    // Receive the next response, skipping unknown packets if enabled
+    async fn recv_page(
+        &mut self,
+        buf: &mut [u8],
+        wait_for: Option<Duration>,
+    ) -> Result<(usize, Reply)> {
+        let deadline = wait_for.map(|duration| Instant::now() + duration);
+        loop {
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let len = self.recv_packet(buf, remaining).await?;
+            match packet::parse_reply(&buf[..len], &self.response_header) {
+                Err(MsqError::MismatchedHeader | MsqError::UnexpectedResponse(_))
+                    if self.skip_unknown_packets =>
+                {
+                    continue
+                }
+                page => return Ok((len, page?)),
+            }
+        }
+    }
+
+    async fn sleep(&self, delay: Duration) -> Result<()> {
+        Self::cancellable(&self.cancel, sleep(delay)).await
+    }
+
+    // Delay before the next request, after `empty_pages` empty responses in
+    // a row and a previous page which was full or not
+    fn page_delay(&self, empty_pages: usize, last_full: bool) -> Duration {
+        if self.skip_delay_when_full && last_full {
+            return Duration::ZERO;
+        }
+        if !self.adaptive_delay || empty_pages == 0 {
+            return self.delay;
+        }
+        let factor = 2u32.saturating_pow(u32::try_from(empty_pages).unwrap_or(u32::MAX));
+        self.delay
+            .checked_mul(factor)
+            .map_or(ADAPTIVE_DELAY_CAP, |delay| delay.min(ADAPTIVE_DELAY_CAP))
+            .max(self.delay)
+    }
+
+    // Empty responses in a row before giving up. With the adaptive delay,
+    // the ones answering requests sent before the delay reached its cap
+    // don't count, so the backoff always gets there
+    fn max_empty_responses(&self) -> usize {
+        let mut backoff = 0;
+        if self.adaptive_delay && !self.delay.is_zero() {
+            while self.page_delay(backoff, false) < ADAPTIVE_DELAY_CAP {
+                backoff += 1;
+            }
+        }
+        backoff + packet::MAX_EMPTY_RESPONSES
+    }
+
+    async fn report_complete(&self, count: usize, last_seed: (Address, u16)) {
+        if let Some(completion) = &self.completion {
+            let _ = completion.send(QueryComplete { count, last_seed }).await;
+        }
+    }
+
+    fn report_progress(&self, pages: usize, servers: usize, end_of_list: bool) {
+        if let Some(progress) = &self.progress {
+            let _ = progress.try_send(Progress {
+                pages,
+                servers,
+                end_of_list,
+            });
+        }
+    }
+
+    // Run the query, giving up with a timeout once past the deadline
+    async fn within_deadline<T>(
+        deadline: Option<Duration>,
+        future: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        match deadline {
+            Some(duration) => timeout(duration, future)
+                .await
+                .unwrap_or(Err(MsqError::Timeout)),
+            None => future.await,
+        }
+    }
+
+    // Run the future unless the token gets cancelled first
+    async fn cancellable<T>(
+        cancel: &Option<CancellationToken>,
+        future: impl Future<Output = T>,
+    ) -> Result<T> {
+        match cancel {
+            Some(token) => tokio::select! {
+                _ = token.cancelled() => Err(MsqError::Cancelled),
+                output = future => Ok(output),
+            },
+            None => Ok(future.await),
+        }
+    }
+
+    // Step the running query: request the next page and wait for it
+    // Checks the filter of a request (strict mode, validation of `raw`
+    // bytes) and returns it null terminated, as sent
+    fn request_filter(&self, region_code: u8, filter_bytes: &[u8], raw: bool) -> Result<Vec<u8>> {
+        let filter_bytes = filter_bytes.strip_suffix(&[0x00]).unwrap_or(filter_bytes);
+        if self.strict && filter_bytes.is_empty() && region_code == Region::All.as_u8() {
+            return Err(MsqError::UnfilteredQuery);
+        }
+        // Only the `\` separators matter, invalid UTF-8 is fine
+        if raw
+            && self.validate
+            && String::from_utf8_lossy(filter_bytes)
+                .parse::<Filter>()
+                .is_err()
+        {
+            return Err(MsqError::MalformedFilter);
+        }
+        let mut filter: Cursor<Vec<u8>> = Cursor::new(Vec::default());
+        filter.write_cstring_bytes(filter_bytes)?;
+        Ok(filter.into_inner())
+    }
+
+    // Sends one page request and waits for the page. A challenge is
+    // answered right away with the same request, up to a few times in a row
+    // in case the master server never accepts it. The last challenge is kept
+    // in `challenge`, even on error, for the next request to include it.
+    async fn exchange(
         &mut self,
+        buf: &mut [u8],
         region_code: u8,
-        filter_str: &str,
-        sender: Sender<(Address, u16)>,
-    ) -> Result<()> {
+        filter_bytes: &[u8],
+        challenge: &mut Option<u32>,
+        (address, port): (Address, u16),
+        wait_for: Option<Duration>,
+    ) -> Result<(usize, Page)> {
+        let mut challenges = 0;
+        loop {
+            self.send(region_code, filter_bytes, *challenge, address, port)
+                .await?;
+            match self.recv_page(buf, wait_for).await? {
+                (len, Reply::Page(page)) => return Ok((len, page)),
+                (_, Reply::Challenge(_)) if challenges == packet::MAX_CHALLENGES => {
+                    return Err(MsqError::UnexpectedResponse(packet::CHALLENGE));
+                }
+                (_, Reply::Challenge(number)) => {
+                    challenges += 1;
+                    *challenge = Some(number);
+                }
+            }
+        }
+    }
+
+    async fn poll_page(&mut self, buf: &mut [u8]) -> Result<Option<(usize, Page)>> {
+        let max_pages = self.max_pages.unwrap_or(usize::MAX);
+        let (
+            region_code,
+            filter_bytes,
+            mut challenge,
+            first,
+            empty_pages,
+            last_full,
+            address,
+            port,
+        ) = match &self.paging {
+            Some(paging) if !paging.end_of_list && paging.pages < max_pages => (
+                paging.region_code,
+                paging.filter_bytes.clone(),
+                paging.challenge,
+                paging.pages == 0,
+                paging.seed.empty_responses(),
+                paging.last_full,
+                paging.seed.address,
+                paging.seed.port,
+            ),
+            _ => {
+                self.paging = None;
+                return Ok(None);
+            }
+        };
+
+        let wait_for = if first {
+            self.rotate().await?;
+            self.initial_timeout
+        } else {
+            self.sleep(self.page_delay(empty_pages, last_full)).await?;
+            self.page_timeout
+        };
+        let result = self
+            .exchange(
+                buf,
+                region_code,
+                &filter_bytes,
+                &mut challenge,
+                (address, port),
+                wait_for,
+            )
+            .await;
+        if let Some(paging) = self.paging.as_mut() {
+            paging.challenge = challenge;
+        }
+        let (len, mut page) = result?;
+
+        let max_empty = self.max_empty_responses();
+        let Some(paging) = self.paging.as_mut() else {
+            return Ok(None);
+        };
+        if let Err(err) = paging.seed.update(&page, max_empty) {
+            self.paging = None;
+            return Err(err);
+        }
+        paging.last_full = page.servers.len() >= FULL_PAGE_SERVERS;
+        // After the seed update, which needs the last server of the page
+        if let Some(max) = self.max_ports_per_ip {
+            page.servers.retain(|(address, port)| {
+                let ports = paging.ports.entry(*address).or_default();
+                if ports.contains(port) {
+                    return true;
+                }
+                if ports.len() >= max {
+                    return false;
+                }
+                ports.push(*port);
+                true
+            });
+        }
+        paging.pages += 1;
+        paging.servers += page.servers.len();
+        paging.end_of_list = page.end_of_list;
+        let (pages, servers) = (paging.pages, paging.servers);
+        let last_seed = (paging.seed.address, paging.seed.port);
+        self.report_progress(pages, servers, page.end_of_list);
+        if page.end_of_list {
+            self.report_complete(servers, last_seed).await;
+        }
+
+        Ok(Some((len, page)))
+    }
+
+    async fn recv_packets(&mut self) -> Result<Vec<Vec<u8>>> {
         let mut buf: [u8; 2048] = [0x00; 2048];
-        let mut last_address: Address = Address::default();
-        let mut last_port: u16 = 0;
-        let mut end_of_list = false;
-        while !end_of_list {
-            let len = self.sock.recv(&mut buf).await?;
-            let mut cursor = Cursor::new(buf[..len].to_vec());
-            if cursor.read_u8_veccheck(&[0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x0A])? {
-                while let Ok(a) = cursor.read_u8() {
-                    let address = Address {
-                        a,
-                        b: cursor.read_u8()?,
-                        c: cursor.read_u8()?,
-                        d: cursor.read_u8()?,
-                    };
-
-                    if address == EMPTY_ADRESS {
-                        end_of_list = true;
-                        break;
-                    }
+        let mut packets = vec![];
+        while let Some((len, _)) = self.poll_page(&mut buf).await? {
+            packets.push(buf[..len].to_vec());
+        }
+        Ok(packets)
+    }
 
-                    let port = cursor.read_u16::<BigEndian>()?;
-                    sender.send((address.clone(), port)).await.unwrap();
+    async fn recv_with(
+        &mut self,
+        opts: &QueryOpts,
+        sender: &Sender<Result<(Address, u16)>>,
+    ) -> Result<QueryStats> {
+        let started = Instant::now();
+        let mut buf: [u8; 2048] = [0x00; 2048];
+        let mut seen = opts.dedup.map(Seen::new);
+        let mut stats = QueryStats::default();
+        let reached = |stats: &QueryStats| opts.max_results.is_some_and(|max| stats.servers >= max);
+        // Not even the first page is needed
+        if reached(&stats) {
+            self.paging = None;
+        }
+        'pages: loop {
+            let page = match self.poll_page(&mut buf).await {
+                Ok(Some((_, page))) => page,
+                Ok(None) => break,
+                // Polling again requests the same page
+                Err(MsqError::Timeout) if stats.retries < opts.retries => {
+                    stats.retries += 1;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            stats.pages += 1;
+            stats.terminated = page.end_of_list;
 
-                    last_address = address;
-                    last_port = port;
+            let mut servers = page.servers.into_iter();
+            while let Some(server) = servers.next() {
+                if seen.as_mut().is_some_and(|seen| !seen.insert(server)) {
+                    stats.duplicates += 1;
+                    continue;
+                }
+                // Nobody left to read the servers
+                if sender.send(Ok(server)).await.is_err() {
+                    self.paging = None;
+                    stats.terminated = false;
+                    break 'pages;
+                }
+                stats.servers += 1;
+                // Stop right away, without requesting the next page
+                if reached(&stats) {
+                    self.paging = None;
+                    stats.terminated = page.end_of_list && servers.len() == 0;
+                    break 'pages;
                 }
-            } else {
-                return Err(Error::new(ErrorKind::Other, "Mismatched starting sequence"));
             }
+        }
+        stats.elapsed = started.elapsed();
+        Ok(stats)
+    }
 
-            sleep(Duration::from_secs(6)).await;
+    async fn recv_list(&mut self) -> Result<QueryResult> {
+        let mut buf: [u8; 2048] = [0x00; 2048];
+        let mut result = QueryResult::default();
+        while let Some((_, page)) = self.poll_page(&mut buf).await? {
+            result.pages += 1;
+            result.terminated = page.end_of_list;
+            result.servers.extend(page.servers);
+        }
+        Ok(result)
+    }
 
-            self.send(region_code, filter_str, last_address.clone(), last_port)
-                .await?;
+    async fn recv(&mut self, sender: Sender<Vec<(Address, u16)>>) -> Result<QueryOutcome> {
+        let mut buf: [u8; 2048] = [0x00; 2048];
+        let mut count = 0;
+        while let Some((_, page)) = self.poll_page(&mut buf).await? {
+            if page.servers.is_empty() {
+                continue;
+            }
+            let len = page.servers.len();
+            // Nobody left to read the servers
+            if sender.send(page.servers).await.is_err() {
+                self.paging = None;
+                break;
+            }
+            count += len;
         }
+        Ok(QueryOutcome::from_count(count))
+    }
+}
 
-        Ok(())
+// Pages received by the task running a query, see `query_page_stream`
+struct PageStream {
+    receiver: Receiver<Result<Vec<(Address, u16)>>>,
+}
+
+impl Stream for PageStream {
+    type Item = Result<Vec<(Address, u16)>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+// Copy of an error sent through a channel and returned as well. The
+// `io::Error` source is not clonable, only its kind and message are kept.
+// Sends through the channel, failing when it stays full longer than `limit`.
+// `false` once the receiver is dropped
+async fn send_within<T>(sender: &Sender<T>, value: T, limit: Option<Duration>) -> Result<bool> {
+    match limit {
+        Some(limit) => match sender.send_timeout(value, limit).await {
+            Ok(()) => Ok(true),
+            Err(SendTimeoutError::Timeout(_)) => Err(MsqError::ChannelStalled),
+            Err(SendTimeoutError::Closed(_)) => Ok(false),
+        },
+        None => Ok(sender.send(value).await.is_ok()),
     }
 }
+
+fn duplicate(err: &MsqError) -> MsqError {
+    match err {
+        MsqError::Io(err) => MsqError::Io(std::io::Error::new(err.kind(), err.to_string())),
+        MsqError::MismatchedHeader => MsqError::MismatchedHeader,
+        MsqError::UnexpectedResponse(kind) => MsqError::UnexpectedResponse(*kind),
+        MsqError::InvalidRegion(code) => MsqError::InvalidRegion(*code),
+        MsqError::InvalidAddress => MsqError::InvalidAddress,
+        MsqError::InvalidRegionCode => MsqError::InvalidRegionCode,
+        MsqError::Timeout => MsqError::Timeout,
+        MsqError::Cancelled => MsqError::Cancelled,
+        MsqError::NotConnected => MsqError::NotConnected,
+        MsqError::InvalidFilterValue(key) => MsqError::InvalidFilterValue(key),
+        MsqError::MalformedFilter => MsqError::MalformedFilter,
+        MsqError::RequestTooLarge(len) => MsqError::RequestTooLarge(*len),
+        MsqError::EmptyResponses(count) => MsqError::EmptyResponses(*count),
+        MsqError::UnfilteredQuery => MsqError::UnfilteredQuery,
+        MsqError::ProxyRefused(code) => MsqError::ProxyRefused(*code),
+        MsqError::ChannelStalled => MsqError::ChannelStalled,
+    }
+}
+
+/// Find every server of a game in one call
+///
+/// Connects a new [`MSQClient`] to the master server and sweeps every
+/// region for the appid, see [`MSQClient::scrape`]. Uses the default
+/// delay of 6 seconds between requests, the adaptive delay (see
+/// [`MSQClient::set_adaptive_delay`]) and a timeout of 10 seconds per
+/// response, so a sweep takes at least a minute.
+///
+/// **NOTE**: The master server does not guarantee a complete list, this
+/// returns as many servers as it could get.
+///
+/// # Arguments
+/// * `master` - The master server's address (EX: `hl2master.steampowered.com:27011`)
+/// * `appid` - The appid to query for (EX: `240`)
+///
+/// # Example
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() -> msq::Result<()> {
+///     let servers = msq::scrape("hl2master.steampowered.com:27011", 240).await?;
+///     println!("Found {} servers", servers.len());
+///     Ok(())
+/// }
+/// ```
+pub async fn scrape(master: &str, appid: u32) -> Result<ServerList> {
+    let mut client = MSQClient::new().await?;
+    client.connect(master).await?;
+    client.set_adaptive_delay(true);
+    client.set_timeout(Some(SCRAPE_TIMEOUT));
+    client.scrape(appid).await
+}
@@ -0,0 +1,315 @@
+use crate::address::{Address, EMPTY_ADRESS};
+use crate::error::{MsqError, Result};
+use crate::filter::Filter;
+use crate::packet::{self, Reply, Seed};
+use crate::region::Region;
+
+use crate::packet_ext::WritePacketExt;
+use socket2::SockRef;
+use std::io::{Cursor, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread::sleep;
+use std::time::Duration;
+
+const DEFAULT_DELAY: Duration = Duration::from_secs(6);
+
+/// The MSQ client driver (non-async/blocking)
+///
+/// * Requires feature: `non-async` (Turned **on** by default)
+/// * Intended to be used with [`Filter`] and [`Region`].
+/// * This uses the standard library's blocking UDP Socket
+///   ([`std::net::UdpSocket`]).
+/// * The async version of this: [`MSQClient`](crate::MSQClient)
+/// * Answers challenges from the master server the same way, see
+///   [`MSQClient`'s challenge handshake](crate::MSQClient#challenge-handshake).
+///
+/// ## Quick Start
+/// ```rust,no_run
+/// use msq::{MSQClientBlock, Region, Filter};
+/// use std::io::Result;
+///
+/// fn main() -> Result<()> {
+///     let mut client = MSQClientBlock::new()?;
+///     client.connect("hl2master.steampowered.com:27011")?;
+///
+///     let servers = client
+///         .query(Region::Europe,  // Restrict query to Europe region
+///             Filter::new()       // Create a Filter builder
+///                 .appid(240)     // appid of 240 (CS:S)
+///                 .nand()         // Start of NAND special filter
+///                     .map("de_dust2")     // Map is de_dust2
+///                     .empty(true)         // Server is empty
+///                 .end()          // End of NAND special filter
+///                 .gametype(&vec!["friendlyfire", "alltalk"]))?;
+///     Ok(())
+/// }
+/// ```
+pub struct MSQClientBlock {
+    sock: UdpSocket,
+    delay: Duration,
+    validate: bool,
+}
+
+impl MSQClientBlock {
+    /// Create a new MSQClientBlock variable and binds the UDP socket to `0.0.0.0:0`
+    pub fn new() -> Result<MSQClientBlock> {
+        let sock = UdpSocket::bind("0.0.0.0:0")?;
+        Ok(Self::from_socket(sock))
+    }
+
+    /// Create a new MSQClientBlock variable from an existing UDP socket
+    ///
+    /// Allows configuring the socket beforehand (EX: binding a specific
+    /// interface or setting options not covered by the client). If the
+    /// socket is already connected, the client is ready to query without
+    /// calling [`connect`](#method.connect).
+    ///
+    /// # Arguments
+    /// * `sock` - A bound [`std::net::UdpSocket`], optionally connected
+    pub fn from_socket(sock: UdpSocket) -> MSQClientBlock {
+        MSQClientBlock {
+            sock,
+            delay: DEFAULT_DELAY,
+            validate: true,
+        }
+    }
+
+    /// Returns the local address the client's socket is bound to
+    ///
+    /// # Example
+    /// ```
+    /// use msq::MSQClientBlock;
+    ///
+    /// let client = MSQClientBlock::new().unwrap();
+    /// assert_ne!(client.local_addr().unwrap().port(), 0);
+    /// ```
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.sock.local_addr()?)
+    }
+
+    /// Set the delay between each request sent to the master server
+    ///
+    /// The master server rate limits its clients, so lowering this below
+    /// the default of 6 seconds may get the client throttled.
+    ///
+    /// # Arguments
+    /// * `delay` - The time to wait before requesting the next page
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay;
+    }
+
+    /// Set whether filters are checked before sending
+    ///
+    /// See [`MSQClient::set_validate`](crate::MSQClient::set_validate), on by default.
+    ///
+    /// # Arguments
+    /// * `on` - `true` to check every filter before sending
+    pub fn set_validate(&mut self, on: bool) {
+        self.validate = on;
+    }
+
+    /// Set the size of the socket's receive buffer (`SO_RCVBUF`)
+    ///
+    /// A larger buffer avoids the kernel dropping packets while the client
+    /// is busy between pages.
+    ///
+    /// **NOTE**: The OS may adjust the value: Linux doubles it and caps it at
+    /// `net.core.rmem_max`, other platforms have their own limits.
+    ///
+    /// # Arguments
+    /// * `bytes` - Requested receive buffer size in bytes
+    pub fn set_recv_buffer(&mut self, bytes: usize) -> Result<()> {
+        SockRef::from(&self.sock).set_recv_buffer_size(bytes)?;
+        Ok(())
+    }
+
+    /// Set the time-to-live (`IP_TTL`) of the packets sent to the master server
+    ///
+    /// # Arguments
+    /// * `ttl` - Time-to-live, between `1` and `255` on most platforms
+    pub fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.sock.set_ttl(ttl)?;
+        Ok(())
+    }
+
+    /// Set how long to wait for every response from the master server
+    ///
+    /// Uses the socket's read timeout. When a response takes longer, the
+    /// query fails with [`MsqError::Timeout`]. Waits forever by default.
+    ///
+    /// # Arguments
+    /// * `timeout` - The time to wait for a response, `None` to wait forever
+    ///   (a zero duration is rejected with an error)
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.sock.set_read_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Connect the client to the given master server address/hostname
+    ///
+    /// Nothing assumes the Steam master's port: mirrors and other games'
+    /// masters listening on any port are queried the same way.
+    ///
+    /// # Arguments
+    /// * `master_server_addr` - The master server's hostname/ip address and port
+    ///   (EX: `hl2master.steampowered.com:27011`)
+    ///
+    /// # Example
+    /// ```no_run
+    /// use msq::MSQClientBlock;
+    /// use std::io::Result;
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut client = MSQClientBlock::new()?;
+    ///     client.connect("hl2master.steampowered.com:27011")?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn connect(&mut self, master_server_addr: &str) -> Result<()> {
+        self.sock.connect(master_server_addr)?;
+        Ok(())
+    }
+
+    /// Query with raw bytes
+    ///
+    /// Returns a Vec list of every server found
+    ///
+    /// # Arguments
+    /// * `region_code` - Region code in u8 (`0x00 - 0x07 / 0xFF`)
+    /// * `filter_str` - Filter in plain string (EX: `\\appid\\240\\map\\de_dust2`)
+    pub fn query_raw(
+        &mut self,
+        region_code: u8,
+        filter_str: impl AsRef<str>,
+    ) -> Result<Vec<(Address, u16)>> {
+        let mut filter: Cursor<Vec<u8>> = Cursor::new(Vec::default());
+        filter.write_cstring(filter_str.as_ref())?;
+        self.query_raw_bytes(region_code, filter.get_ref())
+    }
+
+    /// Query with a pre-built filter byte buffer
+    ///
+    /// See [`MSQClient::query_raw_bytes`](crate::MSQClient::query_raw_bytes),
+    /// the caller owns the correctness of the bytes.
+    ///
+    /// # Arguments
+    /// * `region_code` - Region code in u8 (`0x00 - 0x07 / 0xFF`)
+    /// * `filter_bytes` - Null-terminated filter (EX: `b"\\appid\\240\0"`)
+    pub fn query_raw_bytes(
+        &mut self,
+        region_code: u8,
+        filter_bytes: &[u8],
+    ) -> Result<Vec<(Address, u16)>> {
+        self.query_bytes(region_code, filter_bytes, true)
+    }
+
+    // `query_raw_bytes`, with the filter checked as raw bytes or not (a
+    // `Filter` is checked beforehand)
+    fn query_bytes(
+        &mut self,
+        region_code: u8,
+        filter_bytes: &[u8],
+        raw: bool,
+    ) -> Result<Vec<(Address, u16)>> {
+        let filter_bytes = filter_bytes.strip_suffix(&[0x00]).unwrap_or(filter_bytes);
+        if raw
+            && self.validate
+            && String::from_utf8_lossy(filter_bytes)
+                .parse::<Filter>()
+                .is_err()
+        {
+            return Err(MsqError::MalformedFilter);
+        }
+        let mut filter: Cursor<Vec<u8>> = Cursor::new(Vec::default());
+        filter.write_cstring_bytes(filter_bytes)?;
+        let filter_bytes = filter.get_ref();
+
+        self.send(region_code, filter_bytes, None, EMPTY_ADRESS, 0)?; // First Packet
+        self.recv(region_code, filter_bytes)
+    }
+
+    /// Query with specified Region and Filter
+    ///
+    /// Returns a Vec list of every server found
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    /// * `filter` - [`Filter`] builder (EX: `Filter::new().appid(240).map("de_dust2")`)
+    pub fn query(&mut self, region: Region, filter: Filter) -> Result<Vec<(Address, u16)>> {
+        if self.validate && !filter.is_well_formed() {
+            return Err(MsqError::MalformedFilter);
+        }
+        self.query_bytes(region.as_u8(), filter.as_string().as_bytes(), false)
+    }
+
+    fn send(
+        &mut self,
+        region_code: u8,
+        filter_bytes: &[u8],
+        challenge: Option<u32>,
+        address: Address,
+        port: u16,
+    ) -> Result<()> {
+        let packet = packet::request(region_code, filter_bytes, challenge, &address, port)?;
+        packet::check_sent(self.sock.send(&packet), packet.len())
+    }
+
+    fn recv_packet(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self.sock.recv(buf) {
+            Ok(len) => Ok(len),
+            // Read timeouts surface as either kind depending on the platform
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                Err(MsqError::Timeout)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn recv(&mut self, region_code: u8, filter_bytes: &[u8]) -> Result<Vec<(Address, u16)>> {
+        let mut buf: [u8; 2048] = [0x00; 2048];
+        let mut seed = Seed::default();
+        let mut servers = vec![];
+        let mut challenge = None;
+        let mut challenges = 0;
+        loop {
+            let len = self.recv_packet(&mut buf)?;
+            // Same handshake as the async client: answer right away, a few times at most
+            let mut page = match packet::parse_reply(&buf[..len], &packet::RESPONSE_HEADER)? {
+                Reply::Page(page) => page,
+                Reply::Challenge(_) if challenges == packet::MAX_CHALLENGES => {
+                    return Err(MsqError::UnexpectedResponse(packet::CHALLENGE));
+                }
+                Reply::Challenge(number) => {
+                    challenges += 1;
+                    challenge = Some(number);
+                    self.send(
+                        region_code,
+                        filter_bytes,
+                        challenge,
+                        seed.address,
+                        seed.port,
+                    )?;
+                    continue;
+                }
+            };
+            challenges = 0;
+            seed.update(&page, packet::MAX_EMPTY_RESPONSES)?;
+            servers.append(&mut page.servers);
+
+            if page.end_of_list {
+                break;
+            }
+
+            sleep(self.delay);
+            self.send(
+                region_code,
+                filter_bytes,
+                challenge,
+                seed.address,
+                seed.port,
+            )?;
+        }
+
+        Ok(servers)
+    }
+}
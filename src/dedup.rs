@@ -0,0 +1,109 @@
+use crate::address::Address;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+/// How servers sent more than once are told apart, see
+/// [`MSQClient::set_dedup_mode`](crate::MSQClient::set_dedup_mode)
+///
+/// * Requires feature: `async`
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum DedupMode {
+    /// Remembers every server seen: never wrong, but the memory used grows
+    /// with the number of servers (~40 bytes each)
+    #[default]
+    Exact,
+    /// Remembers the servers seen in a Bloom filter of fixed size
+    ///
+    /// Servers are never sent twice, but a small share of the servers not
+    /// seen yet are taken for repeats and dropped: about `fp_rate` of them
+    /// while fewer than `capacity` servers were seen, more past it.
+    Approximate {
+        /// Number of distinct servers expected
+        capacity: usize,
+        /// Share of new servers wrongly dropped at capacity (EX: `0.001`)
+        fp_rate: f64,
+    },
+}
+
+impl DedupMode {
+    /// Returns the memory in bytes used to remember the servers, `None` when unbounded
+    ///
+    /// # Example
+    /// ```
+    /// use msq::DedupMode;
+    ///
+    /// assert_eq!(DedupMode::Exact.memory_bound(), None);
+    ///
+    /// // ~1.2 KB for 1000 servers at 1%, ~18 KB for 10000 at 0.1%
+    /// let small = DedupMode::Approximate { capacity: 1000, fp_rate: 0.01 };
+    /// assert_eq!(small.memory_bound(), Some(1200));
+    /// let large = DedupMode::Approximate { capacity: 10_000, fp_rate: 0.001 };
+    /// assert_eq!(large.memory_bound(), Some(17976));
+    /// ```
+    pub fn memory_bound(&self) -> Option<usize> {
+        match self {
+            Self::Exact => None,
+            Self::Approximate { capacity, fp_rate } => {
+                Some(bloom_bits(*capacity, *fp_rate).div_ceil(64) * 8)
+            }
+        }
+    }
+}
+
+// Optimal number of bits for `capacity` items at `fp_rate`:
+// -n * ln(p) / ln(2)^2
+fn bloom_bits(capacity: usize, fp_rate: f64) -> usize {
+    let fp_rate = fp_rate.clamp(f64::MIN_POSITIVE, 0.5);
+    let bits = -(capacity.max(1) as f64) * fp_rate.ln() / (2f64.ln() * 2f64.ln());
+    (bits.ceil() as usize).max(64)
+}
+
+// The servers already sent by a query
+pub(crate) enum Seen {
+    Exact(HashSet<(Address, u16)>),
+    Approximate { bits: Vec<u64>, hashes: u32 },
+}
+
+impl Seen {
+    pub fn new(mode: DedupMode) -> Seen {
+        match mode {
+            DedupMode::Exact => Seen::Exact(HashSet::new()),
+            DedupMode::Approximate { capacity, fp_rate } => {
+                let words = bloom_bits(capacity, fp_rate).div_ceil(64);
+                // Optimal number of hashes: m / n * ln(2)
+                let hashes = ((words * 64) as f64 / capacity.max(1) as f64 * 2f64.ln()).round();
+                Seen::Approximate {
+                    bits: vec![0; words],
+                    hashes: (hashes as u32).clamp(1, 32),
+                }
+            }
+        }
+    }
+
+    // Returns whether the server was not seen yet
+    pub fn insert(&mut self, server: (Address, u16)) -> bool {
+        match self {
+            Seen::Exact(seen) => seen.insert(server),
+            Seen::Approximate { bits, hashes } => {
+                let mut hasher = DefaultHasher::new();
+                server.hash(&mut hasher);
+                let hash = hasher.finish();
+                // Double hashing: h1 + i * h2
+                let (h1, h2) = (hash as u32 as u64, (hash >> 32) | 1);
+                let len = bits.len() as u64 * 64;
+
+                let mut new = false;
+                for i in 0..*hashes as u64 {
+                    let bit = h1.wrapping_add(i.wrapping_mul(h2)) % len;
+                    let (word, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+                    if bits[word] & mask == 0 {
+                        bits[word] |= mask;
+                        new = true;
+                    }
+                }
+                new
+            }
+        }
+    }
+}
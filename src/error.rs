@@ -0,0 +1,156 @@
+use core::error::Error;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+/// Errors returned by the MSQ clients and parsers
+///
+/// * The original `io::Error` of a failed socket operation is kept as
+///   the [`source`](Error::source) of `MsqError::Io`, so error reports
+///   (`anyhow`, `thiserror`, ...) can surface the underlying cause.
+/// * Converts into an `io::Error` for code that still uses `io::Result`.
+/// * `MsqError::Io` and the `io::Error` conversions require feature: `std`
+///
+/// # Example
+/// ```
+/// use msq::MsqError;
+/// use std::error::Error;
+/// use std::io;
+///
+/// let err = MsqError::from(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"));
+/// let cause = err.source().unwrap().downcast_ref::<io::Error>().unwrap();
+/// assert_eq!(cause.kind(), io::ErrorKind::ConnectionRefused);
+///
+/// // Back into io::Error, the original error is returned as-is
+/// let io_err: io::Error = err.into();
+/// assert_eq!(io_err.kind(), io::ErrorKind::ConnectionRefused);
+///
+/// // Protocol errors are wrapped and can be recovered
+/// let io_err: io::Error = MsqError::MismatchedHeader.into();
+/// assert!(matches!(
+///     io_err.get_ref().unwrap().downcast_ref::<MsqError>(),
+///     Some(MsqError::MismatchedHeader)
+/// ));
+/// ```
+#[derive(Debug)]
+pub enum MsqError {
+    /// The socket operation failed
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// The response does not start with the master server's header
+    MismatchedHeader,
+    /// The response is a connectionless packet (`0xFF 0xFF 0xFF 0xFF`) of
+    /// another type than a server list, with its type byte (EX: `0x41`, a
+    /// challenge, or `0x6C`, a text message). Challenges are answered by the
+    /// clients, so a challenge is only reported when malformed or repeated
+    /// too many times.
+    UnexpectedResponse(u8),
+    /// The byte is not a known region code
+    InvalidRegion(u8),
+    /// The text is not a dotted quad IPv4 address
+    InvalidAddress,
+    /// The text is not a decimal or hexadecimal region byte
+    InvalidRegionCode,
+    /// The master server did not respond in time
+    Timeout,
+    /// The query was cancelled through its cancellation token
+    Cancelled,
+    /// The client was never connected to a master server
+    NotConnected,
+    /// The value given to the filter key is not accepted by the master server
+    InvalidFilterValue(&'static str),
+    /// The text is not a filter string of complete `\key\value` tokens
+    MalformedFilter,
+    /// The request packet is longer than [`MAX_REQUEST_LEN`](crate::MAX_REQUEST_LEN)
+    /// bytes, or the socket could not send it whole
+    RequestTooLarge(usize),
+    /// The master server kept replying without any server nor end of list
+    EmptyResponses(usize),
+    /// An empty filter with `Region::All` was refused by the client's strict mode
+    UnfilteredQuery,
+    /// The SOCKS5 proxy refused to relay UDP, with its reply code
+    /// (`0xFF` when it requires authentication)
+    ProxyRefused(u8),
+    /// The channel receiving the servers stayed full longer than the
+    /// client's send timeout
+    ChannelStalled,
+}
+
+/// Result type returned throughout msq
+pub type Result<T> = core::result::Result<T, MsqError>;
+
+impl fmt::Display for MsqError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(_) => write!(f, "I/O error"),
+            Self::MismatchedHeader => write!(f, "Mismatched starting sequence"),
+            Self::UnexpectedResponse(kind) => {
+                let name = match kind {
+                    0x41 => "a challenge",
+                    0x6C => "a text message",
+                    _ => "an unknown packet",
+                };
+                write!(
+                    f,
+                    "Master server replied with {} (type {:#04x}) instead of a server list",
+                    name, kind
+                )
+            }
+            Self::InvalidRegion(code) => write!(f, "Invalid region code: {:#04x}", code),
+            Self::InvalidAddress => write!(f, "Invalid address"),
+            Self::InvalidRegionCode => write!(f, "Invalid region code text"),
+            Self::Timeout => write!(f, "Timed out waiting for the master server"),
+            Self::Cancelled => write!(f, "Query cancelled"),
+            Self::NotConnected => write!(f, "Not connected to a master server"),
+            Self::InvalidFilterValue(key) => write!(f, "Invalid value for filter key: {}", key),
+            Self::MalformedFilter => write!(f, "Malformed filter string"),
+            Self::RequestTooLarge(len) => write!(
+                f,
+                "Request of {} bytes is too large to send, shorten the filter",
+                len
+            ),
+            Self::EmptyResponses(count) => {
+                write!(f, "Gave up after {} empty responses in a row", count)
+            }
+            Self::ProxyRefused(code) => {
+                write!(f, "SOCKS5 proxy refused, reply code: {:#04x}", code)
+            }
+            Self::ChannelStalled => write!(
+                f,
+                "Result channel stayed full too long, read it while the query runs"
+            ),
+            Self::UnfilteredQuery => write!(
+                f,
+                "Refused to query every server of every region, add a filter or a region"
+            ),
+        }
+    }
+}
+
+impl Error for MsqError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for MsqError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<MsqError> for io::Error {
+    fn from(err: MsqError) -> Self {
+        match err {
+            MsqError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
@@ -17,6 +17,17 @@
 //!     .map("de_dust2");
 //! ```
 //!
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use crate::error::{MsqError, Result};
+use crate::limits::MAX_FILTER_LEN;
+use crate::region::Region;
+
 #[derive(Clone)]
 enum FilterPropVal {
     Special(Vec<FilterProp>),
@@ -31,11 +42,26 @@ impl FilterPropVal {
         Self::Special(spec.to_owned())
     }
 
-    fn from_tags(tags: &Vec<&str>) -> FilterPropVal {
+    // Number of tokens following a special filter's count, recursing into
+    // nested special filters so their own tokens are included
+    fn token_count(filterprops: &[FilterProp]) -> usize {
+        filterprops
+            .iter()
+            .map(|fp| match &fp.value {
+                Self::Special(nested) => 1 + Self::token_count(nested),
+                _ => 1,
+            })
+            .sum()
+    }
+
+    fn from_tags(tags: &[&str]) -> FilterPropVal {
         let mut fpvtags: Vec<String> = vec![];
 
+        // Repeated tags only lengthen the request, keep the first of each
         for tag in tags {
-            fpvtags.push(String::from(*tag));
+            if !tag.is_empty() && !fpvtags.iter().any(|t| t == tag) {
+                fpvtags.push(String::from(*tag));
+            }
         }
 
         Self::Tags(fpvtags)
@@ -47,7 +73,7 @@ impl FilterPropVal {
                 let mut sstr = String::from("");
 
                 // Start with values count
-                sstr += &format!("{}", filterprops.len());
+                sstr += &format!("{}", Self::token_count(filterprops));
 
                 // Populate the string with inner values
                 for fp in filterprops {
@@ -89,16 +115,216 @@ impl FilterProp {
     fn as_str(&self) -> String {
         format!("\\{}\\{}", self.name, self.value.as_str())
     }
+
+    // Read the next `key\value` token, the whole special filter for nor/nand.
+    // Other values are kept as text, which is all their string depends on.
+    fn parse<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Option<FilterProp>> {
+        let Some(name) = tokens.next() else {
+            return Ok(None);
+        };
+        let value = tokens.next().ok_or(MsqError::MalformedFilter)?;
+        if name.is_empty() {
+            return Err(MsqError::MalformedFilter);
+        }
+
+        let value = match name {
+            // Only a builder block, the master server has no such key
+            "and" => return Err(MsqError::MalformedFilter),
+            "nor" | "nand" => {
+                let mut count: usize = value.parse().map_err(|_| MsqError::MalformedFilter)?;
+                if count == 0 {
+                    return Err(MsqError::MalformedFilter);
+                }
+                let mut props = vec![];
+                while count > 0 {
+                    let prop = Self::parse(tokens)?.ok_or(MsqError::MalformedFilter)?;
+                    let len = FilterPropVal::token_count(core::slice::from_ref(&prop));
+                    count = count.checked_sub(len).ok_or(MsqError::MalformedFilter)?;
+                    props.push(prop);
+                }
+                FilterPropVal::Special(props)
+            }
+            _ => FilterPropVal::Str(String::from(value)),
+        };
+        Ok(Some(FilterProp::new(name, value)))
+    }
+
+    fn tokens(&self, tokens: &mut Vec<(String, String)>) {
+        match &self.value {
+            FilterPropVal::Special(filterprops) => {
+                tokens.push((
+                    self.name.clone(),
+                    format!("{}", FilterPropVal::token_count(filterprops)),
+                ));
+                for fp in filterprops {
+                    fp.tokens(tokens);
+                }
+            }
+            value => tokens.push((self.name.clone(), value.as_str())),
+        }
+    }
+
+    fn debug_str(&self, depth: usize) -> String {
+        let indent = "  ".repeat(depth);
+        match &self.value {
+            FilterPropVal::Special(filterprops) => {
+                let mut sstr = format!(
+                    "{}{}={}  # {}\n",
+                    indent,
+                    self.name,
+                    FilterPropVal::token_count(filterprops),
+                    key_label(&self.name)
+                );
+                for fp in filterprops {
+                    sstr += &fp.debug_str(depth + 1);
+                }
+                sstr
+            }
+            value => format!(
+                "{}{}={}  # {}\n",
+                indent,
+                self.name,
+                value.as_str(),
+                key_label(&self.name)
+            ),
+        }
+    }
+}
+
+/// Kind of server for the legacy `\type\` filter, see [`Filter::server_type`]
+///
+/// | `ServerType` Enum       | Token     | Newer equivalent |
+/// | ----------------------- | --------- | ---------------- |
+/// | `ServerType::Dedicated` | `\type\d` | `\dedicated\1`   |
+/// | `ServerType::Proxy`     | `\type\p` | `\proxy\1`       |
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ServerType {
+    Dedicated,
+    Proxy,
+}
+
+impl ServerType {
+    /// Return the letter of the kind in the `\type\` token
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dedicated => "d",
+            Self::Proxy => "p",
+        }
+    }
+}
+
+/// A game selected by both its appid and gamedir, see [`Filter::game`]
+///
+/// | `Game` Enum       | Title                                | appid  | gamedir      |
+/// | ----------------- | ------------------------------------ | ------ | ------------ |
+/// | `Game::CssSource` | Counter-Strike: Source               | `240`  | `cstrike`    |
+/// | `Game::Csgo`      | Counter-Strike: Global Offensive / 2 | `730`  | `csgo`       |
+/// | `Game::Tf2`       | Team Fortress 2                      | `440`  | `tf`         |
+/// | `Game::Dods`      | Day of Defeat: Source                | `300`  | `dod`        |
+/// | `Game::Hl2dm`     | Half-Life 2: Deathmatch              | `320`  | `hl2mp`      |
+/// | `Game::L4d2`      | Left 4 Dead 2                        | `550`  | `left4dead2` |
+/// | `Game::Gmod`      | Garry's Mod                          | `4000` | `garrysmod`  |
+/// | `Game::Custom`    | Any other game                       | given  | given        |
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Game {
+    CssSource,
+    Csgo,
+    Tf2,
+    Dods,
+    Hl2dm,
+    L4d2,
+    Gmod,
+    /// Any game missing from the table
+    Custom {
+        appid: u32,
+        gamedir: String,
+    },
+}
+
+impl Game {
+    /// Return the appid of the game
+    pub fn appid(&self) -> u32 {
+        match self {
+            Self::CssSource => 240,
+            Self::Csgo => 730,
+            Self::Tf2 => 440,
+            Self::Dods => 300,
+            Self::Hl2dm => 320,
+            Self::L4d2 => 550,
+            Self::Gmod => 4000,
+            Self::Custom { appid, .. } => *appid,
+        }
+    }
+
+    /// Return the gamedir of the game
+    pub fn gamedir(&self) -> &str {
+        match self {
+            Self::CssSource => "cstrike",
+            Self::Csgo => "csgo",
+            Self::Tf2 => "tf",
+            Self::Dods => "dod",
+            Self::Hl2dm => "hl2mp",
+            Self::L4d2 => "left4dead2",
+            Self::Gmod => "garrysmod",
+            Self::Custom { gamedir, .. } => gamedir,
+        }
+    }
+}
+
+/// Maximum length in bytes of a map or gamedir filter value
+///
+/// Source servers keep the map name in a 64 byte buffer (63 characters
+/// plus the terminator), the same limit is applied to gamedir.
+pub const MAX_VALUE_LEN: usize = 63;
+
+// Check a value against the master server's per-value limits
+fn validate_value<'a>(key: &'static str, value: &'a str) -> Result<&'a str> {
+    if value.is_empty() || value.len() > MAX_VALUE_LEN || value.contains(['\\', '\0']) {
+        Err(MsqError::InvalidFilterValue(key))
+    } else {
+        Ok(value)
+    }
+}
+
+// Human readable description of a filter key
+fn key_label(name: &str) -> &'static str {
+    match name {
+        "nor" => "None of the following conditions",
+        "nand" => "Not all of the following conditions",
+        "dedicated" => "Dedicated server",
+        "secure" => "Anti-cheat (VAC) secured",
+        "gamedir" => "Running modification",
+        "map" => "Running map",
+        "linux" => "Running on Linux",
+        "password" => "Password protected",
+        "empty" => "Not empty",
+        "noplayers" => "Empty",
+        "full" => "Not full",
+        "proxy" => "Spectator proxy",
+        "appid" => "Running appid",
+        "napp" => "Not running appid",
+        "white" => "Whitelisted",
+        "gametype" => "All of the tags in sv_tags",
+        "gamedata" => "All of the hidden tags",
+        "gamedataor" => "Any of the hidden tags",
+        "name_match" => "Hostname matching",
+        "version_match" => "Version matching",
+        "collapse_addr_hash" => "One server per IP address",
+        "gameaddr" => "On IP address",
+        "region" => "In region",
+        "type" => "Server type (legacy)",
+        _ => "Unknown filter",
+    }
 }
 
 /// Filter builder - Construct your filter to filter out server results
 ///
 /// * Intended to be used with: [`MSQClient`](crate::MSQClient) and
-/// [`MSQClientBlock`](crate::MSQClientBlock)
+///   [`MSQClientBlock`](crate::MSQClientBlock)
 /// * **NOTE**: Some filters may or may not work as expected depending on
-/// appid/games you try it on. The filter builder methods and string
-/// construction generally follows close to the reference listed out
-/// in the Valve developer wiki.
+///   appid/games you try it on. The filter builder methods and string
+///   construction generally follows close to the reference listed out
+///   in the Valve developer wiki.
 /// * Reference: <https://developer.valvesoftware.com/wiki/Master_Server_Query_Protocol#Filter>
 ///
 /// # Quick Start
@@ -114,11 +340,103 @@ impl FilterProp {
 ///         .gametype(&vec!["friendlyfire", "alltalk"]);
 /// ```
 ///
+#[derive(Clone)]
 pub struct Filter {
     filter_lst: Vec<FilterProp>,
-    in_special: bool,
-    spec_vec: Vec<FilterProp>,
-    special_name: String,
+    // String of `filter_lst`, kept up to date by every builder call
+    filter_str: String,
+    // Open special filters, innermost last
+    specials: Vec<(String, Vec<FilterProp>)>,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Two filters are equal when they send the same top level tokens, in any order
+///
+/// The master server ANDs the top level tokens, so their order does not
+/// change the servers matched. The tokens inside a special filter (nor,
+/// nand) must come in the same order, and unclosed special filters must
+/// match as well.
+///
+/// # Example
+/// ```
+/// use msq::Filter;
+///
+/// let a = Filter::new().appid(240).map("de_dust2").nand().empty(true).full(true).end();
+/// let b = Filter::new().nand().empty(true).full(true).end().map("de_dust2").appid(240);
+/// assert!(a == b);
+/// assert_ne!(a.as_string(), b.as_string());
+///
+/// assert!(Filter::new().appid(240) != Filter::new().appid(730));
+/// assert!(Filter::new().appid(240) != Filter::new().appid(240).napp(500));
+/// assert!(
+///     Filter::new().nor().empty(true).full(true).end()
+///         != Filter::new().nor().full(true).empty(true).end()
+/// );
+/// ```
+impl PartialEq for Filter {
+    fn eq(&self, other: &Self) -> bool {
+        let top_level = |filter: &Filter| {
+            let mut props: Vec<String> = filter.filter_lst.iter().map(|fp| fp.as_str()).collect();
+            props.sort();
+            props
+        };
+        let open = |filter: &Filter| -> Vec<(String, Vec<String>)> {
+            filter
+                .specials
+                .iter()
+                .map(|(name, props)| (name.clone(), props.iter().map(|fp| fp.as_str()).collect()))
+                .collect()
+        };
+        top_level(self) == top_level(other) && open(self) == open(other)
+    }
+}
+
+impl Eq for Filter {}
+
+/// Parses a filter string, as sent to the master server (EX: from [`Filter::as_string`])
+///
+/// The string is read as `\key\value` tokens. The value of `nor` and `nand`
+/// is the number of tokens of the special filter that follow, nested
+/// special filters and their contents included. Other values are kept as
+/// written: parsing then building the string again gives the same string.
+/// Returns [`MsqError::MalformedFilter`] for a key without a value, a
+/// special filter count that is zero or does not match the tokens that
+/// follow, or an `and` key (which only exists in the builder).
+///
+/// # Example
+/// ```
+/// use msq::{Filter, MsqError};
+///
+/// let filter: Filter = "\\appid\\240\\nand\\2\\map\\de_dust2\\noplayers\\1".parse().unwrap();
+/// assert!(filter == Filter::new().appid(240).nand().map("de_dust2").empty(true).end());
+/// assert_eq!(filter.as_string(), "\\appid\\240\\nand\\2\\map\\de_dust2\\noplayers\\1");
+///
+/// assert!(matches!("\\appid".parse::<Filter>(), Err(MsqError::MalformedFilter)));
+/// assert!(matches!("\\nor\\2\\appid\\240".parse::<Filter>(), Err(MsqError::MalformedFilter)));
+/// ```
+impl FromStr for Filter {
+    type Err = MsqError;
+
+    fn from_str(s: &str) -> Result<Filter> {
+        let mut filter = Filter::new();
+        if s.is_empty() {
+            return Ok(filter);
+        }
+
+        let mut tokens = s
+            .strip_prefix('\\')
+            .ok_or(MsqError::MalformedFilter)?
+            .split('\\');
+        while let Some(prop) = FilterProp::parse(&mut tokens)? {
+            filter.add(prop);
+        }
+        Ok(filter)
+    }
 }
 
 impl Filter {
@@ -129,16 +447,217 @@ impl Filter {
     }
 
     /// Returns a string representing the filters
+    ///
+    /// The string is built up as the builder methods are called, so calling
+    /// this repeatedly (EX: once per request) does not rebuild it.
+    ///
+    /// Tokens are emitted in the order the builder methods were called, and
+    /// the contents of a special filter (nor, nand) directly follow its count.
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// let filter = Filter::new()
+    ///     .map("de_dust2")
+    ///     .nor()
+    ///         .appid(240)
+    ///         .linux(true)
+    ///     .end()
+    ///     .secure(true)
+    ///     .nand()
+    ///         .password(true)
+    ///     .end()
+    ///     .dedicated(true);
+    ///
+    /// assert_eq!(
+    ///     filter.as_string(),
+    ///     "\\map\\de_dust2\\nor\\2\\appid\\240\\linux\\1\\secure\\1\\nand\\1\\password\\1\\dedicated\\1"
+    /// );
+    ///
+    /// // The string follows the builder calls made afterwards
+    /// let filter = filter.appid(730).without("map");
+    /// assert_eq!(
+    ///     filter.as_string(),
+    ///     "\\nor\\2\\appid\\240\\linux\\1\\secure\\1\\nand\\1\\password\\1\\dedicated\\1\\appid\\730"
+    /// );
+    /// ```
     pub fn as_string(&self) -> String {
+        self.filter_str.clone()
+    }
+
+    /// Returns whether the filter is well-formed and fits in a request
+    ///
+    /// A quick check of what would make the master server answer with
+    /// nothing rather than fail loudly: every special filter (nor, nand, and)
+    /// is closed with [`end`](#method.end), no value holds a `\0`, the
+    /// string reads back as the same `\key\value` tokens (a value holding
+    /// a `\` usually breaks them) and it fits in a request with any seed
+    /// and challenge number (see [`MAX_REQUEST_LEN`](crate::MAX_REQUEST_LEN)).
+    /// The clients run it before sending, see
+    /// [`MSQClient::set_validate`](crate::MSQClient::set_validate).
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// assert!(Filter::new().appid(240).nor().map("de_dust2").end().is_valid());
+    ///
+    /// assert!(!Filter::new().appid(240).nor().map("de_dust2").is_valid());
+    /// assert!(!Filter::new().map("de_dust2\\secure").is_valid());
+    /// assert!(!Filter::new().map("a\\b\\c").is_valid());
+    /// assert!(!Filter::new().map("de\0dust2").is_valid());
+    /// assert!(!Filter::new().name_match(&"x".repeat(1400)).is_valid());
+    /// ```
+    pub fn is_valid(&self) -> bool {
+        self.filter_str.len() <= MAX_FILTER_LEN && self.is_well_formed()
+    }
+
+    // Same as `is_valid` without the length, left to the request size check
+    pub(crate) fn is_well_formed(&self) -> bool {
+        self.specials.is_empty()
+            && !self.filter_str.contains('\0')
+            && self
+                .filter_str
+                .parse::<Filter>()
+                .is_ok_and(|parsed| parsed.tokens() == self.tokens())
+    }
+
+    /// Returns a human readable breakdown of the filters, one `key=value` per
+    /// line labelled with what it filters (special filter contents indented)
+    ///
+    /// Meant for reading/debugging only, [`as_string`](#method.as_string)
+    /// is what gets sent to the master server.
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// let filter = Filter::new()
+    ///     .appid(240)
+    ///     .nand()
+    ///         .map("de_dust2")
+    ///         .empty(true)
+    ///     .end();
+    ///
+    /// let debug = filter.debug_string();
+    /// assert!(debug.contains("appid=240  # Running appid\n"));
+    /// assert!(debug.contains("nand=2  # Not all of the following conditions\n"));
+    /// assert!(debug.contains("  map=de_dust2  # Running map\n"));
+    /// assert!(debug.contains("  noplayers=1  # Empty\n"));
+    /// ```
+    pub fn debug_string(&self) -> String {
         let mut sstr = String::from("");
 
         for fp in &self.filter_lst {
-            sstr += &fp.as_str();
+            sstr += &fp.debug_str(0);
         }
 
         sstr
     }
 
+    /// Returns every token of the filters as `(key, value)` pairs, in order
+    ///
+    /// Machine readable counterpart of [`debug_string`](#method.debug_string),
+    /// matching [`as_string`](#method.as_string) token for token. A special
+    /// filter (nor, nand) is its key with its count as the value, directly
+    /// followed by the tokens it contains (nested special filters included).
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// let filter = Filter::new()
+    ///     .appid(240)
+    ///     .nor()
+    ///         .map("de_dust2")
+    ///         .gametype(&vec!["alltalk", "hardcore"])
+    ///     .end()
+    ///     .linux(true);
+    ///
+    /// let pairs = |tokens: &[(&str, &str)]| -> Vec<(String, String)> {
+    ///     tokens.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    /// };
+    /// assert_eq!(
+    ///     filter.tokens(),
+    ///     pairs(&[
+    ///         ("appid", "240"),
+    ///         ("nor", "2"),
+    ///         ("map", "de_dust2"),
+    ///         ("gametype", "alltalk,hardcore"),
+    ///         ("linux", "1"),
+    ///     ])
+    /// );
+    /// ```
+    pub fn tokens(&self) -> Vec<(String, String)> {
+        let mut tokens = vec![];
+        for fp in &self.filter_lst {
+            fp.tokens(&mut tokens);
+        }
+        tokens
+    }
+
+    /// Returns the appid set by [`appid`](#method.appid), `None` when not set
+    ///
+    /// Only the top level appid counts: one inside a special filter (nor,
+    /// nand) excludes servers instead. Also reads the appid of a parsed
+    /// filter string, or one set with [`raw_value`](#method.raw_value) if it
+    /// is a number.
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// assert_eq!(Filter::new().appid(240).appid_value(), Some(240));
+    /// assert_eq!(Filter::new().appid(240).appid(730).appid_value(), Some(730));
+    /// assert_eq!(Filter::new().nor().appid(240).end().appid_value(), None);
+    /// assert_eq!(Filter::new().appid_value(), None);
+    /// ```
+    pub fn appid_value(&self) -> Option<u32> {
+        self.filter_lst
+            .iter()
+            .rev()
+            .find(|fp| fp.name == "appid")
+            .and_then(|fp| match &fp.value {
+                FilterPropVal::Uint32(appid) => Some(*appid),
+                FilterPropVal::Str(appid) => appid.parse().ok(),
+                _ => None,
+            })
+    }
+
+    /// Removes every top level token of the given key
+    ///
+    /// Useful to drop a token from a shared base filter (EX: `secure`). The
+    /// key is the name sent to the master server, which can differ from the
+    /// builder method (EX: `empty(true)` emits `noplayers`). Tokens inside
+    /// special filters (nor, nand) are left untouched, and removing a special
+    /// filter's key (EX: `nand`) removes the whole block.
+    ///
+    /// # Arguments
+    /// * `key` - Name of the tokens to remove (EX: `secure`)
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// let base = Filter::new()
+    ///     .appid(240)
+    ///     .secure(true)
+    ///     .nor()
+    ///         .secure(false)
+    ///     .end();
+    ///
+    /// assert_eq!(
+    ///     base.without("secure").as_string(),
+    ///     "\\appid\\240\\nor\\1\\secure\\0"
+    /// );
+    /// ```
+    pub fn without(mut self, key: &str) -> Filter {
+        self.filter_lst.retain(|prop| prop.name != key);
+        self.rebuild_string();
+        self
+    }
+
     /// Returns a new Filter struct, used for string builder
     ///
     /// # Examples
@@ -153,21 +672,123 @@ impl Filter {
     pub fn new() -> Filter {
         Filter {
             filter_lst: vec![],
-            in_special: false,
-            spec_vec: vec![],
-            special_name: String::from(""),
+            filter_str: String::from(""),
+            specials: vec![],
+        }
+    }
+
+    /// Returns a new Filter preset for public gameplay servers running game \[appid\]
+    ///
+    /// Same as chaining `appid(appid)`, `secure(true)`, `dedicated(true)`,
+    /// `empty(false)`, `full(false)` and `password(false)`, which emits:
+    /// `\appid\<appid>\secure\1\dedicated\1\empty\1\full\1\password\0`
+    ///
+    /// # Arguments
+    /// * `appid` - The appid of the server: (EX: `240` (for CS:S))
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// assert_eq!(
+    ///     Filter::public_gameplay(240).as_string(),
+    ///     "\\appid\\240\\secure\\1\\dedicated\\1\\empty\\1\\full\\1\\password\\0"
+    /// );
+    /// ```
+    pub fn public_gameplay(appid: u32) -> Filter {
+        Filter::new()
+            .appid(appid)
+            .secure(true)
+            .dedicated(true)
+            .empty(false)
+            .full(false)
+            .password(false)
+    }
+
+    /// Returns a new Filter preset for SourceTV relays of game \[appid\]
+    ///
+    /// Same as chaining `appid(appid)` and `proxy(true)`, which emits:
+    /// `\appid\<appid>\proxy\1`
+    ///
+    /// # Arguments
+    /// * `appid` - The appid of the game relayed: (EX: `730` (for CS:GO))
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// assert_eq!(Filter::sourcetv(730).as_string(), "\\appid\\730\\proxy\\1");
+    /// ```
+    pub fn sourcetv(appid: u32) -> Filter {
+        Filter::new().appid(appid).proxy(true)
+    }
+
+    /// Returns a new Filter preset for trusted servers running game \[appid\]
+    ///
+    /// Same as chaining `whitelisted(true)`, `secure(true)` and
+    /// `appid(appid)`, which emits: `\white\1\secure\1\appid\<appid>`
+    ///
+    /// # Arguments
+    /// * `appid` - The appid of the server: (EX: `240` (for CS:S))
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// assert_eq!(
+    ///     Filter::trusted(240).as_string(),
+    ///     "\\white\\1\\secure\\1\\appid\\240"
+    /// );
+    /// ```
+    pub fn trusted(appid: u32) -> Filter {
+        Filter::new().whitelisted(true).secure(true).appid(appid)
+    }
+
+    // Tokens of the innermost open special filter, or the top level ones
+    fn props_mut(&mut self) -> &mut Vec<FilterProp> {
+        match self.specials.last_mut() {
+            Some((_, props)) => props,
+            None => &mut self.filter_lst,
+        }
+    }
+
+    // Add the token to the innermost open special filter, or the top level
+    fn add(&mut self, prop: FilterProp) {
+        match self.specials.last_mut() {
+            Some((_, props)) => props.push(prop),
+            None => {
+                self.filter_str += &prop.as_str();
+                self.filter_lst.push(prop);
+            }
+        }
+    }
+
+    fn rebuild_string(&mut self) {
+        self.filter_str.clear();
+        for fp in &self.filter_lst {
+            self.filter_str += &fp.as_str();
         }
     }
 
     fn push(mut self, name: &str, value: FilterPropVal) -> Filter {
-        if self.in_special {
-            self.spec_vec.push(FilterProp::new(name, value));
-        } else {
-            self.filter_lst.push(FilterProp::new(name, value));
+        self.add(FilterProp::new(name, value));
+        self
+    }
+
+    // Drops every token of the name from the innermost open block
+    fn remove(mut self, name: &str) -> Filter {
+        self.props_mut().retain(|prop| prop.name != name);
+        if self.specials.is_empty() {
+            self.rebuild_string();
         }
         self
     }
 
+    // Same as push, but drops any previous token of the same name first
+    fn replace(self, name: &str, value: FilterPropVal) -> Filter {
+        self.remove(name).push(name, value)
+    }
+
     // Generic filter: Boolean
     fn boolean(self, name: &str, switch: bool) -> Filter {
         self.push(name, FilterPropVal::Boolean(switch))
@@ -184,8 +805,9 @@ impl Filter {
     }
 
     // Generic filter: Vector of strings
-    fn vecstr(self, name: &str, tags: &Vec<&str>) -> Filter {
-        if tags.is_empty() {
+    fn vecstr(self, name: &str, tags: &[&str]) -> Filter {
+        // A key without any value would be malformed
+        if tags.iter().all(|tag| tag.is_empty()) {
             self
         } else {
             self.push(name, FilterPropVal::from_tags(tags))
@@ -194,9 +816,7 @@ impl Filter {
 
     // Generic filter: Special (start)
     fn special_start(mut self, name: &str) -> Filter {
-        self.spec_vec.clear();
-        self.in_special = true;
-        self.special_name = String::from(name);
+        self.specials.push((String::from(name), vec![]));
         self
     }
 
@@ -212,8 +832,62 @@ impl Filter {
         self.special_start("nand")
     }
 
-    /// End the special filter (nor, nand)
-    /// You must use this method after each nor/nand special filter method being used
+    /// Groups the following \[x\] conditions, which must all match.
+    /// See [`end`](#method.end) method to see examples on usage.
+    ///
+    /// Top level conditions are already ANDed together by the master server,
+    /// so at top level this emits no token of its own: the grouped
+    /// conditions are written as if they were chained directly. Inside a
+    /// nor/nand block, the group stays a single condition which is met when
+    /// all of its conditions are, written as a nand block alone inside a nor
+    /// block (not "not all of them"), see the NOTE of
+    /// [`maps_any`](#method.maps_any).
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// let grouped = Filter::new()
+    ///     .appid(240)
+    ///     .and()
+    ///         .map("de_dust2")
+    ///         .secure(true)
+    ///     .end()
+    ///     .full(false);
+    /// let flat = Filter::new().appid(240).map("de_dust2").secure(true).full(false);
+    ///
+    /// assert_eq!(grouped.as_string(), flat.as_string());
+    ///
+    /// // Excludes the servers running de_dust2 on Linux, not every server
+    /// // running either
+    /// let nested = Filter::new()
+    ///     .nor()
+    ///         .and()
+    ///             .map("de_dust2")
+    ///             .linux(true)
+    ///         .end()
+    ///     .end();
+    /// assert_eq!(
+    ///     nested.as_string(),
+    ///     "\\nor\\4\\nor\\3\\nand\\2\\map\\de_dust2\\linux\\1"
+    /// );
+    /// ```
+    pub fn and(self) -> Filter {
+        self.special_start("and")
+    }
+
+    /// End the special filter (and, nor, nand)
+    /// You must use this method after each and/nor/nand special filter method being used
+    ///
+    /// Special filters can be nested, each `end` closes the innermost one.
+    /// The count of a special filter covers every token that follows it in
+    /// the block, the tokens of nested special filters included (EX:
+    /// `\nand\3\map\de_dust2\nor\1\empty\1`). A special filter closed
+    /// without any condition adds nothing, its token would be malformed.
+    ///
+    /// Calling this without an open special filter does nothing, in debug
+    /// and release builds alike (EX: `Filter::new().appid(240).end()` is
+    /// the same as `Filter::new().appid(240)`).
     ///
     /// # Examples
     /// Using the NAND filter:
@@ -239,13 +913,67 @@ impl Filter {
     ///     .end()      // Ends the NOR special filter
     ///     .gametype(&vec!["friendlyfire", "alltalk"]);
     /// ```
+    ///
+    /// Nesting a NOR filter inside a NAND filter:
+    /// ```
+    /// use msq::Filter;
+    /// let filter = Filter::new()
+    ///     .appid(240)
+    ///     .nand()     // Exclude servers that has de_dust2 AND...
+    ///         .map("de_dust2")
+    ///         .nor()      // ...is neither password protected NOR full
+    ///             .password(true)
+    ///             .full(true)
+    ///         .end()
+    ///     .end()
+    ///     .secure(true);
+    ///
+    /// assert_eq!(
+    ///     filter.as_string(),
+    ///     "\\appid\\240\\nand\\4\\map\\de_dust2\\nor\\2\\password\\1\\full\\0\\secure\\1"
+    /// );
+    ///
+    /// let filter = Filter::new()
+    ///     .nor()
+    ///         .nand()
+    ///             .map("de_dust2")
+    ///             .empty(true)
+    ///         .end()
+    ///         .nand()
+    ///             .map("cs_office")
+    ///             .linux(true)
+    ///         .end()
+    ///     .end();
+    ///
+    /// assert_eq!(
+    ///     filter.as_string(),
+    ///     "\\nor\\6\\nand\\2\\map\\de_dust2\\noplayers\\1\\nand\\2\\map\\cs_office\\linux\\1"
+    /// );
+    /// ```
     pub fn end(mut self) -> Filter {
-        self.filter_lst.push(FilterProp::new(
-            &self.special_name,
-            FilterPropVal::from_special(&self.spec_vec),
-        ));
-        self.in_special = false;
-        self.special_name = String::from("");
+        let Some((name, props)) = self.specials.pop() else {
+            return self;
+        };
+        // An empty block would be malformed, see `FromStr`
+        if props.is_empty() {
+            return self;
+        }
+        // Only the top level (or an enclosing and) ANDs the conditions
+        let grouped = self
+            .specials
+            .last()
+            .is_some_and(|(outer, _)| outer != "and");
+        if name == "and" && grouped {
+            // Not (not all of them): all of them
+            let nand = FilterProp::new("nand", FilterPropVal::from_special(&props));
+            self.add(FilterProp::new("nor", FilterPropVal::from_special(&[nand])));
+        } else if name == "and" {
+            for prop in props {
+                self.add(prop);
+            }
+        } else {
+            self.add(FilterProp::new(&name, FilterPropVal::from_special(&props)));
+        }
         self
     }
 
@@ -265,20 +993,146 @@ impl Filter {
         self.boolean("secure", hasac)
     }
 
+    /// Servers running the given game, matched by both its appid and gamedir
+    ///
+    /// Sets [`appid`](#method.appid) and [`gamedir`](#method.gamedir) from
+    /// the same [`Game`], replacing any appid or gamedir already set, so the
+    /// two can't disagree. Use `Game::Custom` for a game missing from the
+    /// table, or the raw methods. A `Game::Custom` appid of `0` (no appid
+    /// filter, see [`appid`](#method.appid)) removes the appid already set.
+    ///
+    /// # Arguments
+    /// * `game` - [`Game`] enum (EX: `Game::CssSource`)
+    ///
+    /// # Example
+    /// ```
+    /// use msq::{Filter, Game};
+    ///
+    /// assert_eq!(
+    ///     Filter::new().game(Game::CssSource).as_string(),
+    ///     "\\appid\\240\\gamedir\\cstrike"
+    /// );
+    ///
+    /// let game = Game::Custom { appid: 4020, gamedir: String::from("sven") };
+    /// assert_eq!(
+    ///     Filter::new().gamedir("tf").game(game).as_string(),
+    ///     "\\appid\\4020\\gamedir\\sven"
+    /// );
+    ///
+    /// let game = Game::Custom { appid: 0, gamedir: String::from("sven") };
+    /// assert_eq!(Filter::new().appid(240).game(game).as_string(), "\\gamedir\\sven");
+    /// ```
+    pub fn game(self, game: Game) -> Filter {
+        let filter = match game.appid() {
+            0 => self.remove("appid"),
+            appid => self.appid(appid),
+        };
+        filter.replace("gamedir", FilterPropVal::Str(String::from(game.gamedir())))
+    }
+
     /// Servers running the specified modification (ex: cstrike)
     ///
+    /// Not validated, see [`try_gamedir`](#method.try_gamedir)
+    ///
     /// # Arguments
     /// * `modg` - The modification name (ex: `cstrike`)
-    pub fn gamedir(self, modg: &str) -> Filter {
-        self.string("gamedir", modg)
+    pub fn gamedir(self, modg: impl AsRef<str>) -> Filter {
+        self.string("gamedir", modg.as_ref())
+    }
+
+    /// Servers running the specified modification (ex: cstrike), validated
+    ///
+    /// Returns [`MsqError::InvalidFilterValue`] if the name is empty, longer
+    /// than [`MAX_VALUE_LEN`] bytes, or contains a `\` or `0x00` that would
+    /// break the filter string.
+    ///
+    /// # Arguments
+    /// * `modg` - The modification name (ex: `cstrike`)
+    pub fn try_gamedir(self, modg: impl AsRef<str>) -> Result<Filter> {
+        let modg = validate_value("gamedir", modg.as_ref())?;
+        Ok(self.string("gamedir", modg))
     }
 
     /// Servers running the specified map (ex: cs_italy)
     ///
+    /// Not validated, see [`try_map`](#method.try_map)
+    ///
     /// # Arguments
     /// * `mapn` - The current map it's playing (ex: `cs_italy`)
-    pub fn map(self, mapn: &str) -> Filter {
-        self.string("map", mapn)
+    pub fn map(self, mapn: impl AsRef<str>) -> Filter {
+        self.string("map", mapn.as_ref())
+    }
+
+    /// Servers running the specified map (ex: cs_italy), validated
+    ///
+    /// Returns [`MsqError::InvalidFilterValue`] if the name is empty, longer
+    /// than [`MAX_VALUE_LEN`] bytes, or contains a `\` or `0x00` that would
+    /// break the filter string. A server could never report such a map, so
+    /// the master would silently match nothing.
+    ///
+    /// # Arguments
+    /// * `mapn` - The current map it's playing (ex: `cs_italy`)
+    ///
+    /// # Example
+    /// ```
+    /// use msq::{Filter, MsqError};
+    ///
+    /// let filter = Filter::new().try_map(String::from("de_dust2")).unwrap();
+    /// assert_eq!(filter.as_string(), "\\map\\de_dust2");
+    ///
+    /// let result = Filter::new().try_map("de_".repeat(100));
+    /// assert!(matches!(result, Err(MsqError::InvalidFilterValue("map"))));
+    ///
+    /// let result = Filter::new().try_map("de_dust2\\secure\\1");
+    /// assert!(matches!(result, Err(MsqError::InvalidFilterValue("map"))));
+    /// ```
+    pub fn try_map(self, mapn: impl AsRef<str>) -> Result<Filter> {
+        let mapn = validate_value("map", mapn.as_ref())?;
+        Ok(self.string("map", mapn))
+    }
+
+    /// Servers running any of the specified maps
+    ///
+    /// The master server has no OR token, so this is written as "not all of
+    /// the maps are missing": a nand block holding one single-token nor
+    /// block per map (`\nand\4\nor\1\map\a\nor\1\map\b`). With a
+    /// single map, this is the same as [`map`](#method.map), and an empty
+    /// slice adds nothing.
+    ///
+    /// **NOTE**: This relies on the master server evaluating nested special
+    /// filters, which is not documented. If the results look wrong, run one
+    /// query per map instead and merge the results with
+    /// [`ServerList::merge`](crate::ServerList::merge).
+    ///
+    /// # Arguments
+    /// * `maps` - The maps to match (EX: `&["de_dust2", "de_inferno"]`)
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// let filter = Filter::new().appid(240).maps_any(&["de_dust2", "de_inferno"]);
+    /// assert_eq!(
+    ///     filter.as_string(),
+    ///     "\\appid\\240\\nand\\4\\nor\\1\\map\\de_dust2\\nor\\1\\map\\de_inferno"
+    /// );
+    /// assert_eq!(
+    ///     Filter::new().maps_any(&["cs_office"]).as_string(),
+    ///     Filter::new().map("cs_office").as_string()
+    /// );
+    /// ```
+    pub fn maps_any(self, maps: &[&str]) -> Filter {
+        match maps {
+            [] => self,
+            [mapn] => self.map(mapn),
+            _ => {
+                let mut filter = self.nand();
+                for mapn in maps {
+                    filter = filter.nor().map(mapn).end();
+                }
+                filter.end()
+            }
+        }
     }
 
     /// Servers running on a Linux platform
@@ -305,6 +1159,27 @@ impl Filter {
         self.boolean("full", !is_full)
     }
 
+    /// Servers of the specified kind, using the legacy `\type\` shorthand
+    ///
+    /// Emits `\type\d` or `\type\p`, as found in older tools and captured
+    /// requests. These predate the [`dedicated`](#method.dedicated) and
+    /// [`proxy`](#method.proxy) keys, which should be preferred since not
+    /// every master server still honors `\type\`.
+    ///
+    /// # Arguments
+    /// * `kind` - [`ServerType`] enum (`ServerType::Dedicated` / `ServerType::Proxy`)
+    ///
+    /// # Example
+    /// ```
+    /// use msq::{Filter, ServerType};
+    ///
+    /// assert_eq!(Filter::new().server_type(ServerType::Dedicated).as_string(), "\\type\\d");
+    /// assert_eq!(Filter::new().server_type(ServerType::Proxy).as_string(), "\\type\\p");
+    /// ```
+    pub fn server_type(self, kind: ServerType) -> Filter {
+        self.string("type", kind.as_str())
+    }
+
     /// Servers that are spectator proxies
     ///
     /// # Arguments
@@ -315,16 +1190,51 @@ impl Filter {
 
     /// Servers that are running game \[appid\]
     ///
+    /// The master server treats `\appid\0` as no appid filter at all, so
+    /// `appid(0)` is a no-op and adds no token rather than matching app 0.
+    ///
+    /// Only one appid is kept: setting it again replaces the previous one
+    /// (last wins), and the token moves to the end of the filter. Inside a
+    /// special filter (nor, nand) this only applies to that special filter.
+    ///
     /// # Arguments
     /// * `appid` - The appid of the server: (EX: `240` (for CS:S))
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// assert_eq!(Filter::new().appid(240).as_string(), "\\appid\\240");
+    /// assert_eq!(Filter::new().appid(0).as_string(), "");
+    /// assert_eq!(
+    ///     Filter::new().appid(240).secure(true).appid(730).as_string(),
+    ///     "\\secure\\1\\appid\\730"
+    /// );
+    /// ```
     pub fn appid(self, appid: u32) -> Filter {
-        self.uint32("appid", appid)
+        if appid == 0 {
+            self
+        } else {
+            self.replace("appid", FilterPropVal::Uint32(appid))
+        }
     }
 
     /// Servers that are NOT running game \[appid\]
     ///
+    /// Each call appends another `\napp\` token, unlike [`appid`](#method.appid).
+    /// Repeated tokens are ANDed: servers running any of the given appids
+    /// are excluded.
+    ///
     /// # Arguments
     /// * `appid` - The appid of the server: (EX: `240` (for CS:S))
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// let filter = Filter::new().napp(500).napp(550);
+    /// assert_eq!(filter.as_string(), "\\napp\\500\\napp\\550");
+    /// ```
     pub fn napp(self, appid: u32) -> Filter {
         self.uint32("napp", appid)
     }
@@ -341,6 +1251,25 @@ impl Filter {
         }
     }
 
+    /// Servers that have players but are not full
+    ///
+    /// Same as chaining `empty(false)` and `full(false)`, which emits:
+    /// `\empty\1\full\1`
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// assert_eq!(
+    ///     Filter::new().joinable().as_string(),
+    ///     Filter::new().empty(false).full(false).as_string()
+    /// );
+    /// assert_eq!(Filter::new().joinable().as_string(), "\\empty\\1\\full\\1");
+    /// ```
+    pub fn joinable(self) -> Filter {
+        self.empty(false).full(false)
+    }
+
     /// Servers that are whitelisted
     ///
     /// # Arguments
@@ -362,8 +1291,20 @@ impl Filter {
     ///     .gametype(&vec!["friendlyfire", "alltalk"]);
     /// ```
     ///
-    /// If you put in an empty vector, it will return nothing
-    pub fn gametype(self, tags: &Vec<&str>) -> Filter {
+    /// If you put in an empty vector (or only empty tags), no token is added
+    /// rather than a malformed `\gametype\` without value. Same for
+    /// [`gamedata`](#method.gamedata) and [`gamedataor`](#method.gamedataor).
+    ///
+    /// **NOTE**: Repeated tags are only sent once, in the order they first
+    /// appear (same for [`gamedata`](#method.gamedata) and
+    /// [`gamedataor`](#method.gamedataor)):
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// let filter = Filter::new().gametype(&vec!["alltalk", "friendlyfire", "alltalk"]);
+    /// assert_eq!(filter.as_string(), "\\gametype\\alltalk,friendlyfire");
+    /// ```
+    pub fn gametype(self, tags: &[&str]) -> Filter {
         self.vecstr("gametype", tags)
     }
 
@@ -371,15 +1312,60 @@ impl Filter {
     ///
     /// # Arguments
     /// * `tags` - A vector of strings which represents a tag from sv_tags
-    pub fn gamedata(self, tags: &Vec<&str>) -> Filter {
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// // Empty tags add nothing, EX: from an empty config entry
+    /// assert_eq!(Filter::new().appid(550).gamedata(&[]).as_string(), "\\appid\\550");
+    /// assert_eq!(Filter::new().gamedataor(&[""]).as_string(), "");
+    /// ```
+    pub fn gamedata(self, tags: &[&str]) -> Filter {
         self.vecstr("gamedata", tags)
     }
 
+    /// Servers with all of the given `key:value` pair(s) in their 'hidden' tags
+    ///
+    /// For games encoding their tags as `key:value`. Each pair is formatted
+    /// as `key:value` and the tags are joined with `,`, same as
+    /// [`gamedata`](#method.gamedata): `\gamedata\key1:value1,key2:value2`.
+    ///
+    /// **NOTE**: The master server has no escape syntax, the keys and values
+    /// are written as-is. A `,` would split the pair into two tags and a `\`
+    /// would end the token, so neither may appear in a key or value. A `:` is
+    /// only a convention of the game: the master server matches the whole
+    /// tag, so `("a:b", "c")` and `("a", "b:c")` are the same tag `a:b:c`.
+    ///
+    /// # Arguments
+    /// * `pairs` - The keys and values of the tags (EX: `&[("mode", "coop")]`)
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// let filter = Filter::new()
+    ///     .appid(550)
+    ///     .gamedata_kv(&[("mode", "coop"), ("difficulty", "expert")]);
+    /// assert_eq!(
+    ///     filter.as_string(),
+    ///     "\\appid\\550\\gamedata\\mode:coop,difficulty:expert"
+    /// );
+    /// ```
+    pub fn gamedata_kv(self, pairs: &[(&str, &str)]) -> Filter {
+        let tags: Vec<String> = pairs
+            .iter()
+            .map(|(key, value)| format!("{}:{}", key, value))
+            .collect();
+        let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+        self.vecstr("gamedata", &tags)
+    }
+
     /// Servers with any of the given tag(s) in their 'hidden' tags (L4D2)
     ///
     /// # Arguments
     /// * `tags` - A vector of strings which represents a tag from sv_tags
-    pub fn gamedataor(self, tags: &Vec<&str>) -> Filter {
+    pub fn gamedataor(self, tags: &[&str]) -> Filter {
         self.vecstr("gamedataor", tags)
     }
 
@@ -399,6 +1385,37 @@ impl Filter {
         self.string("version_match", ver)
     }
 
+    /// Servers running exactly version \[version\], without wildcards
+    ///
+    /// Emits the same `\version_match\` token as [`version_match`](#method.version_match),
+    /// with `ver` written as given. Returns [`MsqError::InvalidFilterValue`]
+    /// if `ver` is empty, contains a `*` (which would widen the match), or a
+    /// `\` or `0x00` that would break the filter string.
+    ///
+    /// **NOTE**: The master server compares versions as strings, not numbers:
+    /// `1.0` does not match a server reporting `1.0.0`.
+    ///
+    /// # Arguments
+    /// * `ver` - String of the exact version (EX: `1.0.0.71`)
+    ///
+    /// # Example
+    /// ```
+    /// use msq::{Filter, MsqError};
+    ///
+    /// let filter = Filter::new().appid(240).version_exact("1.0.0.71").unwrap();
+    /// assert_eq!(filter.as_string(), "\\appid\\240\\version_match\\1.0.0.71");
+    ///
+    /// let result = Filter::new().version_exact("1.0.*");
+    /// assert!(matches!(result, Err(MsqError::InvalidFilterValue("version_match"))));
+    /// assert!(Filter::new().version_exact("").is_err());
+    /// ```
+    pub fn version_exact(self, ver: &str) -> Result<Filter> {
+        if ver.is_empty() || ver.contains(['*', '\\', '\0']) {
+            return Err(MsqError::InvalidFilterValue("version_match"));
+        }
+        Ok(self.string("version_match", ver))
+    }
+
     /// Return only one server for each unique IP address matched
     ///
     /// # Arguments
@@ -414,4 +1431,87 @@ impl Filter {
     pub fn gameaddr(self, ipaddr: &str) -> Filter {
         self.string("gameaddr", ipaddr)
     }
+
+    /// Return only the server on the specified IP address and port
+    ///
+    /// Emits `\gameaddr\<ip>:<port>`. The Steam master servers honor the
+    /// port (per the Valve developer wiki), other masters may only match the IP.
+    ///
+    /// # Arguments
+    /// * `ipaddr` - String of the IP address to match
+    /// * `port` - Port of the server to match
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// assert_eq!(Filter::new().gameaddr("1.2.3.4").as_string(), "\\gameaddr\\1.2.3.4");
+    /// assert_eq!(
+    ///     Filter::new().gameaddr_port("1.2.3.4", 27015).as_string(),
+    ///     "\\gameaddr\\1.2.3.4:27015"
+    /// );
+    /// ```
+    pub fn gameaddr_port(self, ipaddr: &str, port: u16) -> Filter {
+        self.string("gameaddr", &format!("{}:{}", ipaddr, port))
+    }
+
+    /// Servers located in the specified region
+    ///
+    /// Emits `\region\<code>` with the region's byte code in decimal, see
+    /// [`raw_region`](#method.raw_region) for codes [`Region`] doesn't model.
+    ///
+    /// # Arguments
+    /// * `region` - [`Region`] enum (`Region::USEast` - `Region::Africa` / `Region::All`)
+    pub fn region(self, region: Region) -> Filter {
+        self.raw_region(region.as_u8())
+    }
+
+    /// Servers located in the region of the specified raw byte code
+    ///
+    /// Emits `\region\<code>` in decimal. The code is not validated, it is
+    /// up to the caller to send one the master server understands.
+    ///
+    /// # Arguments
+    /// * `code` - Region code in u8 (EX: `0x03` for Europe)
+    ///
+    /// # Example
+    /// ```
+    /// use msq::{Filter, Region};
+    ///
+    /// assert_eq!(Filter::new().raw_region(0x2A).as_string(), "\\region\\42");
+    /// assert_eq!(
+    ///     Filter::new().region(Region::Europe).as_string(),
+    ///     Filter::new().raw_region(0x03).as_string()
+    /// );
+    /// ```
+    pub fn raw_region(self, code: u8) -> Filter {
+        self.uint32("region", code as u32)
+    }
+
+    /// Adds a `\key\value` token with a wire-ready value, sent byte for byte
+    ///
+    /// For values that come already encoded (EX: percent-encoded from a
+    /// URL, `de%5Fdust2`): decoding them first then encoding them again
+    /// would corrupt them. The builder never escapes nor encodes anything,
+    /// every method writes its value as-is, but this one also takes any key,
+    /// so a value can be forwarded without picking the matching method.
+    ///
+    /// **NOTE**: Nothing is checked: a `\` in the key or value adds tokens and
+    /// the master server decides what the value means (it does not decode
+    /// percent-encoding itself).
+    ///
+    /// # Arguments
+    /// * `key` - The filter key (EX: `map`)
+    /// * `value` - The value exactly as it should be sent (EX: `de%5Fdust2`)
+    ///
+    /// # Example
+    /// ```
+    /// use msq::Filter;
+    ///
+    /// let filter = Filter::new().appid(240).raw_value("map", "de%5Fdust2");
+    /// assert_eq!(filter.as_string(), "\\appid\\240\\map\\de%5Fdust2");
+    /// ```
+    pub fn raw_value(self, key: &str, value: &str) -> Filter {
+        self.string(key, value)
+    }
 }
@@ -28,15 +28,21 @@
 //! [dependencies]
 //! msq = { version = "0.2", default-features = false, features = ["non-async"] }
 //! ```
+//! * For [`Filter`] and [`Region`] **only**, under `no_std` (requires `alloc`):
+//! ```toml
+//! [dependencies]
+//! msq = { version = "0.2", default-features = false, features = ["alloc"] }
+//! ```
 //!
 //! # Quick Start
 //! The following example covers the primary functionalities of this library
 //! and should be quick on understanding how to use the library.
 //!
 //! ## Async version
-//! ```rust
-//! use msq::{MSQClient, Region, Filter};
+//! ```rust,no_run
+//! use msq::{Address, MSQClient, Region, Filter};
 //! use std::io::Result;
+//! use tokio::sync::mpsc;
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<()> {
@@ -46,7 +52,16 @@
 //!     // Connect to the master server
 //!     client.connect("hl2master.steampowered.com:27011").await?;
 //!
-//!     let servers = client
+//!     // Servers are sent through the channel as they arrive
+//!     // An error ending the query early is sent last
+//!     let (sender, mut receiver) = mpsc::channel::<msq::Result<(Address, u16)>>(64);
+//!     tokio::spawn(async move {
+//!         while let Some(Ok((address, port))) = receiver.recv().await {
+//!             println!("{}.{}.{}.{}:{}", address.a, address.b, address.c, address.d, port);
+//!         }
+//!     });
+//!
+//!     client
 //!         .query(Region::Europe,  // Restrict query to Europe region
 //!             Filter::new()       // Create a Filter builder
 //!                 .appid(240)     // appid of 240 (CS:S)
@@ -54,7 +69,8 @@
 //!                     .map("de_dust2")     // Map is de_dust2
 //!                     .empty(true)         // Server is empty
 //!                 .end()          // End of NAND special filter
-//!                 .gametype(&vec!["friendlyfire", "alltalk"])).await?;
+//!                 .gametype(&vec!["friendlyfire", "alltalk"]),
+//!             sender).await?;
 //!
 //!     // nand filter excludes servers that has de_dust2 as
 //!     // its map and is empty
@@ -70,7 +86,7 @@
 //! If you don't want to use async, then a blocking version is available.
 //! The methods functionalities and names should matches its async
 //! counterpart.
-//! ```rust
+//! ```rust,no_run
 //! use msq::{MSQClientBlock, Region, Filter};
 //! use std::io::Result;
 //!
@@ -91,12 +107,62 @@
 //! }
 //! ```
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+mod address;
+#[cfg(feature = "async")]
 mod client;
+#[cfg(feature = "non-async")]
+mod client_block;
+#[cfg(feature = "async")]
+mod dedup;
+mod error;
+#[cfg(feature = "alloc")]
 mod filter;
+#[cfg(feature = "alloc")]
+mod limits;
+#[cfg(feature = "std")]
+mod packet;
+#[cfg(feature = "std")]
 mod packet_ext;
+pub mod prelude;
+#[cfg(feature = "rdns")]
+mod rdns;
 mod region;
+#[cfg(feature = "replay")]
+mod replay;
+#[cfg(feature = "std")]
+mod server_list;
+#[cfg(feature = "socks5")]
+mod socks5;
 
-pub use crate::client::Address;
+#[cfg(feature = "std")]
+pub use crate::address::to_socket_addrs;
+#[cfg(feature = "std")]
+pub use crate::address::Address;
+#[cfg(feature = "async")]
+pub use crate::client::scrape;
+#[cfg(feature = "async")]
 pub use crate::client::MSQClient;
-pub use crate::filter::Filter;
+#[cfg(feature = "async")]
+pub use crate::client::{
+    Progress, QueryComplete, QueryOpts, QueryOutcome, QueryResult, QueryStats,
+};
+#[cfg(feature = "non-async")]
+pub use crate::client_block::MSQClientBlock;
+#[cfg(feature = "async")]
+pub use crate::dedup::DedupMode;
+pub use crate::error::{MsqError, Result};
+#[cfg(feature = "alloc")]
+pub use crate::filter::{Filter, Game, ServerType, MAX_VALUE_LEN};
+#[cfg(feature = "alloc")]
+pub use crate::limits::MAX_REQUEST_LEN;
 pub use crate::region::Region;
+#[cfg(feature = "replay")]
+pub use crate::replay::replay;
+#[cfg(feature = "std")]
+pub use crate::server_list::ServerList;
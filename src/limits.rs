@@ -0,0 +1,21 @@
+// Sizes of the request packet, shared by the packet builder and the filter
+// checks (which don't need `std`)
+
+/// Maximum length in bytes of a request packet sent to the master server
+///
+/// Header, region, seed and filter included. Longer requests would not fit
+/// a single unfragmented datagram on common links and the master server
+/// silently drops them, so they are rejected before sending with
+/// [`MsqError::RequestTooLarge`](crate::MsqError::RequestTooLarge).
+pub const MAX_REQUEST_LEN: usize = 1400;
+
+// Challenge number appended to the requests once the master server asked
+// for one (little endian u32)
+pub(crate) const CHALLENGE_LEN: usize = 4;
+
+// Longest seed, terminator included: `255.255.255.255:65535\0`
+const MAX_SEED_LEN: usize = 22;
+
+// Longest filter fitting a request with the longest seed and a challenge:
+// header, region, seed, filter terminator and challenge number
+pub(crate) const MAX_FILTER_LEN: usize = MAX_REQUEST_LEN - 1 - 1 - MAX_SEED_LEN - 1 - CHALLENGE_LEN;
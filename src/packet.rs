@@ -0,0 +1,185 @@
+use crate::address::{Address, EMPTY_ADRESS};
+use crate::error::{MsqError, Result};
+use crate::limits::MAX_REQUEST_LEN;
+use crate::packet_ext::{ReadPacketExt, WritePacketExt};
+use byteorder::WriteBytesExt;
+use std::io::{self, Cursor, Write};
+
+pub(crate) const RESPONSE_HEADER: [u8; 6] = [0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x0A];
+// Prefix of every connectionless packet, followed by its type byte
+const CONNECTIONLESS: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+pub(crate) const MAX_EMPTY_RESPONSES: usize = 3;
+// Type byte of a challenge reply, followed by the challenge number
+pub(crate) const CHALLENGE: u8 = 0x41;
+// Challenges answered in a row for the same request before giving up
+pub(crate) const MAX_CHALLENGES: usize = 3;
+
+// Request packet for the page following the `address:port` seed, with the
+// challenge number last when the master server asked for one
+pub(crate) fn request(
+    region_code: u8,
+    filter_bytes: &[u8],
+    challenge: Option<u32>,
+    address: &Address,
+    port: u16,
+) -> Result<Vec<u8>> {
+    let mut cursor: Cursor<Vec<u8>> = Cursor::new(Vec::default());
+    cursor.write_u8(0x31)?;
+    cursor.write_u8(region_code)?;
+    // Seed: plain decimal `ip:port` without padding, `0.0.0.0:0` for the first page
+    cursor.write_cstring(&format!("{}:{}", address, port))?;
+    cursor.write_all(filter_bytes)?;
+    if let Some(challenge) = challenge {
+        cursor.write_all(&challenge.to_le_bytes())?;
+    }
+
+    let packet = cursor.into_inner();
+    if packet.len() > MAX_REQUEST_LEN {
+        return Err(MsqError::RequestTooLarge(packet.len()));
+    }
+    Ok(packet)
+}
+
+// `EMSGSIZE`: the datagram is larger than the socket/link accepts. There is
+// no stable `io::ErrorKind` for it, so the raw OS code is checked, only on
+// the targets whose value is known. Elsewhere a too large request is still
+// caught when fewer bytes than the packet are sent.
+// Linux/Android: include/uapi/asm-generic/errno.h
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const EMSGSIZE: Option<i32> = Some(90);
+// macOS/iOS and the BSDs: sys/errno.h
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+))]
+const EMSGSIZE: Option<i32> = Some(40);
+// Windows: `WSAEMSGSIZE` in winerror.h
+#[cfg(windows)]
+const EMSGSIZE: Option<i32> = Some(10040);
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    windows
+)))]
+const EMSGSIZE: Option<i32> = None;
+
+// Check that the whole request packet of `len` bytes was sent
+pub(crate) fn check_sent(sent: io::Result<usize>, len: usize) -> Result<()> {
+    match sent {
+        Ok(sent) if sent < len => Err(MsqError::RequestTooLarge(len)),
+        Ok(_) => Ok(()),
+        Err(err) if EMSGSIZE.is_some() && err.raw_os_error() == EMSGSIZE => {
+            Err(MsqError::RequestTooLarge(len))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Servers of a single response packet
+pub(crate) struct Page {
+    pub servers: Vec<(Address, u16)>,
+    pub end_of_list: bool,
+}
+
+// Either a page of servers or a challenge to answer
+pub(crate) enum Reply {
+    Page(Page),
+    Challenge(u32),
+}
+
+// Same as `parse_page`, but a challenge reply carrying its 4 bytes number
+// (little endian) is not an error
+pub(crate) fn parse_reply(packet: &[u8], header: &[u8]) -> Result<Reply> {
+    match (parse_page(packet, header), packet) {
+        (
+            Err(MsqError::UnexpectedResponse(CHALLENGE)),
+            [0xFF, 0xFF, 0xFF, 0xFF, CHALLENGE, a, b, c, d, ..],
+        ) => Ok(Reply::Challenge(u32::from_le_bytes([*a, *b, *c, *d]))),
+        (page, _) => Ok(Reply::Page(page?)),
+    }
+}
+
+pub(crate) fn parse_page(packet: &[u8], header: &[u8]) -> Result<Page> {
+    let mut cursor = Cursor::new(packet.to_vec());
+    // A packet shorter than the header is just as unknown
+    if !cursor.read_u8_veccheck(header).unwrap_or(false) {
+        // Another type of connectionless packet is a reply, not junk
+        return Err(match packet {
+            [0xFF, 0xFF, 0xFF, 0xFF, kind, ..]
+                if header.starts_with(&CONNECTIONLESS) && header.get(4) != Some(kind) =>
+            {
+                MsqError::UnexpectedResponse(*kind)
+            }
+            _ => MsqError::MismatchedHeader,
+        });
+    }
+
+    let mut servers = vec![];
+    while (cursor.position() as usize) < packet.len() {
+        let [a, b, c, d] = cursor.read_u32_be()?.to_be_bytes();
+        let address = Address { a, b, c, d };
+
+        // Checked before reading the port: whatever follows the terminator
+        // (its zero port, padding or garbage) is never parsed
+        if address == EMPTY_ADRESS {
+            return Ok(Page {
+                servers,
+                end_of_list: true,
+            });
+        }
+
+        let port = cursor.read_u16_be()?;
+        servers.push((address, port));
+    }
+
+    Ok(Page {
+        servers,
+        end_of_list: false,
+    })
+}
+
+// Keeps the seed of the next request across the pages of a query
+#[derive(Default)]
+pub(crate) struct Seed {
+    pub address: Address,
+    pub port: u16,
+    empty_responses: usize,
+}
+
+impl Seed {
+    // Empty responses in a row since the last page with servers
+    pub fn empty_responses(&self) -> usize {
+        self.empty_responses
+    }
+
+    // Fails once more than `max_empty` empty responses came in a row
+    pub fn update(&mut self, page: &Page, max_empty: usize) -> Result<()> {
+        match page.servers.last() {
+            Some((address, port)) => {
+                self.address = *address;
+                self.port = *port;
+                self.empty_responses = 0;
+            }
+            // A valid header without any record nor terminator is a keepalive,
+            // only retry the same seed a bounded number of times in a row
+            None if !page.end_of_list => {
+                self.empty_responses += 1;
+                if self.empty_responses > max_empty {
+                    return Err(MsqError::EmptyResponses(self.empty_responses));
+                }
+            }
+            None => (),
+        }
+        Ok(())
+    }
+}
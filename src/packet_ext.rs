@@ -1,8 +1,11 @@
-use byteorder::{ReadBytesExt, WriteBytesExt};
-use std::io::{Cursor, Result};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Cursor, Error, ErrorKind, Result};
 
 pub trait ReadPacketExt: ReadBytesExt {
     fn read_u8_veccheck(&mut self, src: &[u8]) -> Result<bool>;
+    // The master server protocol sends its integers in network byte order
+    fn read_u16_be(&mut self) -> Result<u16>;
+    fn read_u32_be(&mut self) -> Result<u32>;
 }
 
 impl ReadPacketExt for Cursor<Vec<u8>> {
@@ -15,10 +18,19 @@ impl ReadPacketExt for Cursor<Vec<u8>> {
         }
         Ok(true)
     }
+
+    fn read_u16_be(&mut self) -> Result<u16> {
+        self.read_u16::<BigEndian>()
+    }
+
+    fn read_u32_be(&mut self) -> Result<u32> {
+        self.read_u32::<BigEndian>()
+    }
 }
 
 pub trait WritePacketExt: WriteBytesExt {
     fn write_cstring(&mut self, src: &str) -> Result<()>;
+    fn write_cstring_bytes(&mut self, src: &[u8]) -> Result<()>;
 }
 
 impl WritePacketExt for Cursor<Vec<u8>> {
@@ -34,4 +46,19 @@ impl WritePacketExt for Cursor<Vec<u8>> {
         self.write_u8(0x00)?; // 0x00 Terminated
         Ok(())
     }
+
+    fn write_cstring_bytes(&mut self, src: &[u8]) -> Result<()> {
+        // An interior 0x00 would terminate the string early
+        if src.contains(&0x00) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Interior null byte in C string",
+            ));
+        }
+        for code in src {
+            self.write_u8(*code)?;
+        }
+        self.write_u8(0x00)?; // 0x00 Terminated
+        Ok(())
+    }
 }
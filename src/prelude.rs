@@ -0,0 +1,22 @@
+//! Re-exports the commonly used types for a typical query
+//!
+//! ```
+//! use msq::prelude::*;
+//!
+//! let filter = Filter::new().appid(240);
+//! let region = Region::Europe;
+//! ```
+#[cfg(feature = "std")]
+pub use crate::address::{to_socket_addrs, Address};
+#[cfg(feature = "async")]
+pub use crate::client::{
+    MSQClient, Progress, QueryComplete, QueryOpts, QueryOutcome, QueryResult, QueryStats,
+};
+#[cfg(feature = "non-async")]
+pub use crate::client_block::MSQClientBlock;
+pub use crate::error::MsqError;
+#[cfg(feature = "alloc")]
+pub use crate::filter::{Filter, Game, ServerType};
+pub use crate::region::Region;
+#[cfg(feature = "std")]
+pub use crate::server_list::ServerList;
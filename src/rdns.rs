@@ -0,0 +1,38 @@
+use crate::address::Address;
+
+// Best-effort PTR lookup of the address through the OS resolver (blocking)
+#[cfg(unix)]
+pub(crate) fn reverse_lookup(address: &Address) -> Option<String> {
+    use std::ffi::CStr;
+    use std::mem;
+    use std::ptr;
+
+    // NI_MAXHOST
+    let mut host = [0 as libc::c_char; 1025];
+    let ret = unsafe {
+        let mut addr: libc::sockaddr_in = mem::zeroed();
+        addr.sin_family = libc::AF_INET as libc::sa_family_t;
+        addr.sin_addr.s_addr = u32::from_ne_bytes(address.octets());
+        libc::getnameinfo(
+            &addr as *const libc::sockaddr_in as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+            host.as_mut_ptr(),
+            host.len() as libc::socklen_t,
+            ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    if ret != 0 {
+        return None;
+    }
+
+    // The buffer is null-terminated on success
+    let host = unsafe { CStr::from_ptr(host.as_ptr()) };
+    host.to_str().ok().map(String::from)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn reverse_lookup(_address: &Address) -> Option<String> {
+    None
+}
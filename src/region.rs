@@ -1,9 +1,9 @@
-use std::io::{Error, ErrorKind, Result};
+use crate::error::{MsqError, Result};
 
 /// Region enum to restrict the servers region the query searches for
 ///
 /// * Intended to be used with: [`MSQClient`](crate::MSQClient) and
-/// [`MSQClientBlock`](crate::MSQClientBlock)
+///   [`MSQClientBlock`](crate::MSQClientBlock)
 ///
 /// # Reference
 /// | `Region` Enum          | Region            | Byte |
@@ -18,7 +18,21 @@ use std::io::{Error, ErrorKind, Result};
 /// | `Region::Africa`       | Africa            | 0x07 |
 /// | `Region::All`          | Everyone | 0xFF |
 ///
-#[derive(Debug, PartialEq)]
+/// The default region is `Region::All`, the whole world, which is what a
+/// query without any region preference asks for.
+///
+/// ```rust
+/// use msq::Region;
+///
+/// assert_eq!(Region::default(), Region::All);
+///
+/// // Copied when passed by value
+/// let region = Region::Europe;
+/// let copy = region;
+/// assert_eq!(region.as_u8(), copy.as_u8());
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum Region {
     USEast,
     USWest,
@@ -28,10 +42,53 @@ pub enum Region {
     Australia,
     MiddleEast,
     Africa,
+    #[default]
     All,
 }
 
 impl Region {
+    /// North America: `USEast`, `USWest`
+    ///
+    /// Groups of regions to sweep one query at a time, as matchmakers scope
+    /// their searches. `SouthAmerica` is not part of any group.
+    ///
+    /// # Example
+    /// ```rust
+    /// use msq::Region;
+    ///
+    /// assert_eq!(Region::NORTH_AMERICA, [Region::USEast, Region::USWest]);
+    /// assert_eq!(
+    ///     Region::EUROPE_REGIONS,
+    ///     [Region::Europe, Region::MiddleEast, Region::Africa]
+    /// );
+    /// assert_eq!(Region::ASIA_PACIFIC, [Region::Asia, Region::Australia]);
+    /// ```
+    ///
+    /// Sweeping a group, merging the results:
+    /// ```rust,no_run
+    /// use msq::{Filter, MSQClient, Region, ServerList};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> msq::Result<()> {
+    /// let mut client = MSQClient::new().await?;
+    /// client.connect("hl2master.steampowered.com:27011").await?;
+    ///
+    /// let mut servers = ServerList::new();
+    /// for region in Region::EUROPE_REGIONS {
+    ///     servers.extend(client.query_vec(*region, Filter::new().appid(240)).await?.servers);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub const NORTH_AMERICA: &'static [Region] = &[Region::USEast, Region::USWest];
+
+    /// Europe and its neighbouring regions (EMEA): `Europe`, `MiddleEast`, `Africa`
+    pub const EUROPE_REGIONS: &'static [Region] =
+        &[Region::Europe, Region::MiddleEast, Region::Africa];
+
+    /// Asia and Oceania: `Asia`, `Australia`
+    pub const ASIA_PACIFIC: &'static [Region] = &[Region::Asia, Region::Australia];
+
     /// Return raw u8 byte code of its specified region
     ///
     /// # Example
@@ -43,8 +100,12 @@ impl Region {
     ///
     /// let region_hex_str = format!("{:#04x}", Region::Europe.as_u8());
     /// assert_eq!(&region_hex_str, "0x03");
+    ///
+    /// // Also usable in const contexts
+    /// const EUROPE: u8 = Region::Europe.as_u8();
+    /// assert_eq!(EUROPE, 0x03);
     /// ```
-    pub fn as_u8(&self) -> u8 {
+    pub const fn as_u8(&self) -> u8 {
         match self {
             Self::USEast => 0x00,
             Self::USWest => 0x01,
@@ -58,6 +119,95 @@ impl Region {
         }
     }
 
+    /// Return the canonical lowercase name of its specified region
+    ///
+    /// Does not allocate and is usable in const contexts, EX: for URL paths
+    /// or metric labels.
+    ///
+    /// # Example
+    /// ```rust
+    /// use msq::Region;
+    ///
+    /// assert_eq!(Region::USEast.as_str(), "useast");
+    /// assert_eq!(Region::USWest.as_str(), "uswest");
+    /// assert_eq!(Region::SouthAmerica.as_str(), "southamerica");
+    /// assert_eq!(Region::Europe.as_str(), "europe");
+    /// assert_eq!(Region::Asia.as_str(), "asia");
+    /// assert_eq!(Region::Australia.as_str(), "australia");
+    /// assert_eq!(Region::MiddleEast.as_str(), "middleeast");
+    /// assert_eq!(Region::Africa.as_str(), "africa");
+    /// assert_eq!(Region::All.as_str(), "all");
+    ///
+    /// const EUROPE: &str = Region::Europe.as_str();
+    /// assert_eq!(EUROPE, "europe");
+    /// ```
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::USEast => "useast",
+            Self::USWest => "uswest",
+            Self::SouthAmerica => "southamerica",
+            Self::Europe => "europe",
+            Self::Asia => "asia",
+            Self::Australia => "australia",
+            Self::MiddleEast => "middleeast",
+            Self::Africa => "africa",
+            Self::All => "all",
+        }
+    }
+
+    /// Parse a region from its byte code written as text
+    ///
+    /// Accepts decimal (EX: `"3"`, `"255"`) and `0x` prefixed hexadecimal
+    /// (EX: `"0x03"`, `"0xFF"`) codes, as received from a query parameter.
+    /// Returns [`MsqError::InvalidRegionCode`] when the text is not a byte,
+    /// or [`MsqError::InvalidRegion`] when the byte is not a known region.
+    ///
+    /// # Example
+    /// ```rust
+    /// use msq::{MsqError, Region};
+    ///
+    /// assert_eq!(Region::from_str_code("0x03").unwrap(), Region::Europe);
+    /// assert_eq!(Region::from_str_code("3").unwrap(), Region::Europe);
+    /// assert_eq!(Region::from_str_code("255").unwrap(), Region::All);
+    /// assert_eq!(Region::from_str_code("0xff").unwrap(), Region::All);
+    ///
+    /// assert!(matches!(Region::from_str_code("8"), Err(MsqError::InvalidRegion(8))));
+    /// assert!(matches!(Region::from_str_code("256"), Err(MsqError::InvalidRegionCode)));
+    /// assert!(matches!(Region::from_str_code("europe"), Err(MsqError::InvalidRegionCode)));
+    /// ```
+    pub fn from_str_code(code: &str) -> Result<Self> {
+        let code = code.trim();
+        let byte = match code.strip_prefix("0x").or_else(|| code.strip_prefix("0X")) {
+            Some(hex) => u8::from_str_radix(hex, 16),
+            None => code.parse(),
+        };
+        Self::from_u8(byte.map_err(|_| MsqError::InvalidRegionCode)?)
+    }
+
+    /// Parse a region from its raw u8 byte code
+    ///
+    /// Lossless both ways: every region parsed back from its
+    /// [`as_u8`](#method.as_u8) is the same region, and every byte parsed
+    /// into a region gives the same byte back. A byte that is not a known
+    /// region is not dropped either, [`MsqError::InvalidRegion`] carries it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use msq::{MsqError, Region};
+    ///
+    /// assert_eq!(Region::from_u8(0x03).unwrap(), Region::Europe);
+    ///
+    /// for code in 0..=u8::MAX {
+    ///     match Region::from_u8(code) {
+    ///         Ok(region) => assert_eq!(region.as_u8(), code),
+    ///         Err(MsqError::InvalidRegion(raw)) => {
+    ///             assert_eq!(raw, code);
+    ///             assert!((0x08..0xFF).contains(&code));
+    ///         }
+    ///         Err(err) => panic!("unexpected error: {}", err),
+    ///     }
+    /// }
+    /// ```
     pub fn from_u8(code: u8) -> Result<Self> {
         match code {
             0x00 => Ok(Self::USEast),
@@ -69,7 +219,7 @@ impl Region {
             0x06 => Ok(Self::MiddleEast),
             0x07 => Ok(Self::Africa),
             0xFF => Ok(Self::All),
-            _ => Err(Error::new(ErrorKind::Other, "Invalid code")),
+            _ => Err(MsqError::InvalidRegion(code)),
         }
     }
 }
@@ -0,0 +1,104 @@
+use crate::address::Address;
+use crate::error::Result;
+use crate::packet::{self, Reply};
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Instant;
+
+// Writes every datagram of a session to a text log, one per line:
+// `<direction> <milliseconds since the start> <hex payload>`, with `>` for
+// requests sent and `<` for packets received
+pub(crate) struct Recorder {
+    file: File,
+    started: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Recorder> {
+        Ok(Recorder {
+            file: File::create(path)?,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn request(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write('>', bytes)
+    }
+
+    pub fn response(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write('<', bytes)
+    }
+
+    // Unbuffered, so the log is complete up to a crash
+    fn write(&mut self, direction: char, bytes: &[u8]) -> Result<()> {
+        let mut line = format!("{} {} ", direction, self.started.elapsed().as_millis());
+        for byte in bytes {
+            let _ = write!(line, "{:02x}", byte);
+        }
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Replays a session recorded by [`MSQClient::set_record_path`](crate::MSQClient::set_record_path)
+///
+/// * Requires feature: `replay`
+/// * Parses the recorded packets received in order, without any network,
+///   and returns the servers the query found (duplicates included) up to
+///   the end of the list. The requests and timings are not used.
+///
+/// # Arguments
+/// * `path` - The recorded log file
+///
+/// # Errors
+/// * [`MsqError::Io`](crate::MsqError::Io) if the file can't be read or a line is malformed
+/// * The same parse errors as the recorded query (EX: [`MsqError::MismatchedHeader`](crate::MsqError::MismatchedHeader))
+pub fn replay(path: impl AsRef<Path>) -> Result<Vec<(Address, u16)>> {
+    let log = fs::read_to_string(path)?;
+    let mut servers = vec![];
+    for (number, line) in log.lines().enumerate() {
+        let (direction, payload) = parse_line(line).ok_or_else(|| malformed(number + 1))?;
+        if direction != "<" {
+            continue;
+        }
+
+        let bytes = decode_hex(payload).ok_or_else(|| malformed(number + 1))?;
+        // Answered by the client with another request, no servers
+        let Reply::Page(page) = packet::parse_reply(&bytes, &packet::RESPONSE_HEADER)? else {
+            continue;
+        };
+        servers.extend(page.servers);
+        if page.end_of_list {
+            break;
+        }
+    }
+    Ok(servers)
+}
+
+// Splits a line into its direction and hex payload
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let mut fields = line.splitn(3, ' ');
+    let direction = fields.next().filter(|d| *d == "<" || *d == ">")?;
+    fields.next()?.parse::<u128>().ok()?;
+    Some((direction, fields.next()?))
+}
+
+fn decode_hex(payload: &str) -> Option<Vec<u8>> {
+    if !payload.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..payload.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(payload.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn malformed(number: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed recording line {}", number),
+    )
+}
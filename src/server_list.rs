@@ -0,0 +1,237 @@
+use crate::address::{self, Address};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddrV4;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Servers found by a query, without duplicates
+///
+/// * Requires feature: `std`
+/// * Keeps the order in which the servers were first seen. A server sent
+///   again (EX: repeated across pages) is only listed once.
+/// * Can be built from any iterator of `(Address, u16)`.
+///
+/// # Example
+/// ```
+/// use msq::{Address, ServerList};
+///
+/// let a = Address { a: 1, b: 1, c: 1, d: 1 };
+/// let b = Address { a: 2, b: 2, c: 2, d: 2 };
+/// let servers: ServerList = vec![
+///     (a, 27015),
+///     (b, 27015),
+///     (a, 27016),
+///     (a, 27015),
+/// ]
+/// .into_iter()
+/// .collect();
+///
+/// assert_eq!(servers.len(), 3);
+/// assert_eq!(servers.by_ip()[&a], vec![27015, 27016]);
+/// assert_eq!(
+///     servers.to_socket_addrs().next(),
+///     Some("1.1.1.1:27015".parse().unwrap())
+/// );
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ServerList {
+    servers: Vec<(Address, u16)>,
+    seen: HashSet<(Address, u16)>,
+}
+
+impl ServerList {
+    /// Returns a new empty ServerList
+    pub fn new() -> ServerList {
+        Self::default()
+    }
+
+    /// Unions several lists into one, without duplicates
+    ///
+    /// Servers keep the order in which they were first seen: the servers of
+    /// the first list, then those of the second list not listed yet, etc.
+    ///
+    /// # Arguments
+    /// * `lists` - The lists to merge (EX: results of several queries)
+    ///
+    /// # Example
+    /// ```
+    /// use msq::{Address, ServerList};
+    ///
+    /// let a = Address { a: 1, b: 1, c: 1, d: 1 };
+    /// let b = Address { a: 2, b: 2, c: 2, d: 2 };
+    /// let europe: ServerList = vec![(a, 27015), (b, 27015)]
+    ///     .into_iter()
+    ///     .collect();
+    /// let asia: ServerList = vec![(b, 27016), (a, 27015)]
+    ///     .into_iter()
+    ///     .collect();
+    ///
+    /// let merged = ServerList::merge([europe, asia]);
+    /// assert_eq!(merged.as_slice(), [(a, 27015), (b, 27015), (b, 27016)]);
+    /// ```
+    pub fn merge(lists: impl IntoIterator<Item = ServerList>) -> ServerList {
+        lists.into_iter().flatten().collect()
+    }
+
+    /// Adds a server to the end of the list, returns `false` if it was already listed
+    ///
+    /// # Arguments
+    /// * `server` - The server's address and port
+    pub fn insert(&mut self, server: (Address, u16)) -> bool {
+        if self.seen.insert(server) {
+            self.servers.push(server);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns whether the server is listed
+    pub fn contains(&self, server: &(Address, u16)) -> bool {
+        self.seen.contains(server)
+    }
+
+    /// Returns the number of servers
+    pub fn len(&self) -> usize {
+        self.servers.len()
+    }
+
+    /// Returns whether the list has no server
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// Iterates over the servers in the order they were first seen
+    pub fn iter(&self) -> std::slice::Iter<'_, (Address, u16)> {
+        self.servers.iter()
+    }
+
+    /// Returns the servers as a slice, in the order they were first seen
+    pub fn as_slice(&self) -> &[(Address, u16)] {
+        &self.servers
+    }
+
+    /// Returns the list sorted by address, then by port
+    ///
+    /// The master server sends servers in an order that varies between
+    /// queries, sorting gives a reproducible output (tests, diffs, UIs).
+    ///
+    /// # Example
+    /// ```
+    /// use msq::{Address, ServerList};
+    ///
+    /// let a = Address { a: 10, b: 0, c: 0, d: 1 };
+    /// let b = Address { a: 9, b: 255, c: 0, d: 1 };
+    /// let servers: ServerList = vec![(a, 27016), (b, 27015), (a, 27015)]
+    ///     .into_iter()
+    ///     .collect();
+    ///
+    /// assert_eq!(
+    ///     servers.sorted().as_slice(),
+    ///     [(b, 27015), (a, 27015), (a, 27016)]
+    /// );
+    /// ```
+    pub fn sorted(mut self) -> ServerList {
+        self.servers.sort();
+        self
+    }
+
+    /// Groups the ports listed under each address
+    ///
+    /// Useful to audit hosts running several servers.
+    pub fn by_ip(&self) -> HashMap<Address, Vec<u16>> {
+        let mut grouped: HashMap<Address, Vec<u16>> = HashMap::new();
+        for (address, port) in &self.servers {
+            grouped.entry(*address).or_default().push(*port);
+        }
+        grouped
+    }
+
+    /// Maps the servers into [`SocketAddrV4`]s, see [`to_socket_addrs`](crate::to_socket_addrs)
+    pub fn to_socket_addrs(&self) -> impl Iterator<Item = SocketAddrV4> + '_ {
+        address::to_socket_addrs(self.servers.iter().copied())
+    }
+
+    /// Maps the servers into [`SocketAddrV4`]s, yielding at most `per_second` of them per second
+    ///
+    /// Glue toward A2S queries (with any A2S crate) which paces the queries
+    /// instead of hitting every server at once. This is only a rate-limited
+    /// iterator over the addresses: the first comes right away, and each
+    /// following one once `1 / per_second` seconds passed since the previous
+    /// one. A consumer slower than the rate is never made to catch up.
+    ///
+    /// **NOTE**: The wait blocks the current thread (`std::thread::sleep`),
+    /// in async code pace with a timer (EX: `tokio::time::interval`) instead.
+    ///
+    /// # Arguments
+    /// * `per_second` - Maximum number of addresses per second (`0` is taken as `1`)
+    ///
+    /// # Example
+    /// ```
+    /// use msq::{Address, ServerList};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let servers: ServerList = (1..=5)
+    ///     .map(|d| (Address { a: 10, b: 0, c: 0, d }, 27015))
+    ///     .collect();
+    ///
+    /// let started = Instant::now();
+    /// let addrs: Vec<_> = servers.throttled(20).collect();
+    ///
+    /// // 5 addresses at 20 per second: 4 waits of 50ms
+    /// assert_eq!(addrs.len(), 5);
+    /// assert!(started.elapsed() >= Duration::from_millis(200));
+    /// ```
+    pub fn throttled(&self, per_second: u32) -> impl Iterator<Item = SocketAddrV4> + '_ {
+        let interval = Duration::from_secs(1) / per_second.max(1);
+        let mut due: Option<Instant> = None;
+        self.to_socket_addrs().inspect(move |_| {
+            if let Some(at) = due {
+                sleep(at.saturating_duration_since(Instant::now()));
+            }
+            due = Some(Instant::now() + interval);
+        })
+    }
+}
+
+impl FromIterator<(Address, u16)> for ServerList {
+    fn from_iter<I: IntoIterator<Item = (Address, u16)>>(iter: I) -> Self {
+        let mut servers = ServerList::new();
+        servers.extend(iter);
+        servers
+    }
+}
+
+impl Extend<(Address, u16)> for ServerList {
+    fn extend<I: IntoIterator<Item = (Address, u16)>>(&mut self, iter: I) {
+        for server in iter {
+            self.insert(server);
+        }
+    }
+}
+
+impl IntoIterator for ServerList {
+    type Item = (Address, u16);
+    type IntoIter = std::vec::IntoIter<(Address, u16)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.servers.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ServerList {
+    type Item = &'a (Address, u16);
+    type IntoIter = std::slice::Iter<'a, (Address, u16)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.servers.iter()
+    }
+}
+
+impl PartialEq for ServerList {
+    fn eq(&self, other: &Self) -> bool {
+        self.servers == other.servers
+    }
+}
+
+impl Eq for ServerList {}
@@ -0,0 +1,113 @@
+use crate::error::{MsqError, Result};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const VERSION: u8 = 0x05;
+const NO_AUTH: u8 = 0x00;
+const NO_ACCEPTABLE_METHODS: u8 = 0xFF;
+const UDP_ASSOCIATE: u8 = 0x03;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+// A UDP association with a SOCKS5 proxy (RFC 1928), relaying the datagrams
+// between the client and a single master server
+pub(crate) struct Socks5Relay {
+    // The association only lasts as long as this connection
+    _control: TcpStream,
+    pub proxy_addr: String,
+    pub relay: SocketAddr,
+    header: Vec<u8>,
+}
+
+impl Socks5Relay {
+    pub async fn associate(proxy_addr: &str, target: SocketAddrV4) -> Result<Socks5Relay> {
+        let mut control = TcpStream::connect(proxy_addr).await?;
+
+        // Greeting, without authentication
+        control.write_all(&[VERSION, 1, NO_AUTH]).await?;
+        let mut choice = [0u8; 2];
+        control.read_exact(&mut choice).await?;
+        if choice[0] != VERSION {
+            return Err(malformed().into());
+        }
+        if choice[1] != NO_AUTH {
+            return Err(MsqError::ProxyRefused(NO_ACCEPTABLE_METHODS));
+        }
+
+        // The client's own address is not known yet (0.0.0.0:0)
+        control
+            .write_all(&[VERSION, UDP_ASSOCIATE, 0, ATYP_IPV4, 0, 0, 0, 0, 0, 0])
+            .await?;
+        let mut reply = [0u8; 4];
+        control.read_exact(&mut reply).await?;
+        if reply[0] != VERSION {
+            return Err(malformed().into());
+        }
+        if reply[1] != 0x00 {
+            return Err(MsqError::ProxyRefused(reply[1]));
+        }
+        let ip: IpAddr = match reply[3] {
+            ATYP_IPV4 => {
+                let mut octets = [0u8; 4];
+                control.read_exact(&mut octets).await?;
+                Ipv4Addr::from(octets).into()
+            }
+            ATYP_IPV6 => {
+                let mut octets = [0u8; 16];
+                control.read_exact(&mut octets).await?;
+                octets.into()
+            }
+            _ => return Err(malformed().into()),
+        };
+        let port = control.read_u16().await?;
+
+        // An unspecified relay address means the proxy's own address
+        let relay = if ip.is_unspecified() {
+            SocketAddr::new(control.peer_addr()?.ip(), port)
+        } else {
+            SocketAddr::new(ip, port)
+        };
+
+        let mut header = vec![0, 0, 0, ATYP_IPV4];
+        header.extend_from_slice(&target.ip().octets());
+        header.extend_from_slice(&target.port().to_be_bytes());
+
+        Ok(Socks5Relay {
+            _control: control,
+            proxy_addr: String::from(proxy_addr),
+            relay,
+            header,
+        })
+    }
+
+    // Prefix the datagram with the master server's address for the relay
+    pub fn wrap(&self, packet: &[u8]) -> Vec<u8> {
+        let mut wrapped = self.header.clone();
+        wrapped.extend_from_slice(packet);
+        wrapped
+    }
+
+    // Strip the relay's header in place and return the payload length,
+    // fragments (unsupported) and malformed datagrams are emptied
+    pub fn unwrap(buf: &mut [u8], len: usize) -> usize {
+        let addr_len = match buf.get(3) {
+            Some(&ATYP_IPV4) => 4,
+            Some(&ATYP_IPV6) => 16,
+            Some(&ATYP_DOMAIN) => buf.get(4).map_or(0, |len| 1 + *len as usize),
+            _ => return 0,
+        };
+        let start = 4 + addr_len + 2;
+        if len < start || buf[2] != 0 {
+            return 0;
+        }
+        buf.copy_within(start..len, 0);
+        len - start
+    }
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed SOCKS5 reply")
+}
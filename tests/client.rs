@@ -0,0 +1,1842 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use common::{page, parse_request, MockMaster};
+use futures_core::Stream;
+use msq::{
+    Address, DedupMode, Filter, MSQClient, MsqError, QueryComplete, QueryOpts, QueryOutcome,
+    Region, MAX_REQUEST_LEN,
+};
+use std::future::poll_fn;
+use std::pin::pin;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{channel, Receiver};
+use tokio_util::sync::CancellationToken;
+
+async fn connect(master: &MockMaster) -> MSQClient {
+    let mut client = MSQClient::new().await.unwrap();
+    client.connect(&master.addr()).await.unwrap();
+    client.set_delay(Duration::ZERO);
+    client
+}
+
+// Servers received before the channel closes or an error is sent
+async fn collect(mut receiver: Receiver<msq::Result<(Address, u16)>>) -> Vec<(Address, u16)> {
+    let mut servers = vec![];
+    while let Some(Ok(server)) = receiver.recv().await {
+        servers.push(server);
+    }
+    servers
+}
+
+fn addr(a: u8, b: u8, c: u8, d: u8) -> Address {
+    Address { a, b, c, d }
+}
+
+#[tokio::test]
+async fn query_appids_yields_union() {
+    let master = MockMaster::spawn(|_, request| {
+        let (_, _, filter) = parse_request(request);
+        if filter.ends_with("\\appid\\240") {
+            vec![page(&[([1, 1, 1, 1], 27015), ([2, 2, 2, 2], 27015)], true)]
+        } else {
+            vec![page(&[([2, 2, 2, 2], 27015), ([3, 3, 3, 3], 27016)], true)]
+        }
+    })
+    .await;
+    let mut client = connect(&master).await;
+
+    let (sender, receiver) = channel(16);
+    client
+        .query_appids(Region::All, &[240, 440], Filter::new().secure(true), sender)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        collect(receiver).await,
+        vec![
+            (addr(1, 1, 1, 1), 27015),
+            (addr(2, 2, 2, 2), 27015),
+            (addr(3, 3, 3, 3), 27016),
+        ]
+    );
+    let filters: Vec<String> = master
+        .requests()
+        .iter()
+        .map(|request| parse_request(request).2)
+        .collect();
+    assert_eq!(
+        filters,
+        vec!["\\secure\\1\\appid\\240", "\\secure\\1\\appid\\440"]
+    );
+}
+
+#[tokio::test]
+async fn query_raw_bytes_replays_filter() {
+    let master = MockMaster::scripted(vec![page(&[([1, 2, 3, 4], 27015)], true)]).await;
+    let mut client = connect(&master).await;
+
+    let (sender, receiver) = channel(16);
+    client
+        .query_raw_bytes(0x03, b"\\appid\\240\0", sender)
+        .await
+        .unwrap();
+
+    assert_eq!(collect(receiver).await, vec![(addr(1, 2, 3, 4), 27015)]);
+    assert_eq!(
+        master.requests(),
+        vec![b"\x31\x030.0.0.0:0\0\\appid\\240\0".to_vec()]
+    );
+}
+
+#[tokio::test]
+async fn seed_string_layout() {
+    let master = MockMaster::scripted(vec![
+        page(&[([10, 0, 0, 1], 27015), ([1, 0, 20, 3], 8)], false),
+        page(&[([10, 0, 0, 2], 27015)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+
+    let (sender, receiver) = channel(16);
+    client
+        .query(Region::Europe, Filter::new().appid(240), sender)
+        .await
+        .unwrap();
+
+    assert_eq!(collect(receiver).await.len(), 3);
+    // Per the Valve developer wiki: decimal octets and port without leading
+    // zeros, seed then filter each null-terminated
+    assert_eq!(
+        master.requests(),
+        vec![
+            b"\x31\x030.0.0.0:0\0\\appid\\240\0".to_vec(),
+            b"\x31\x031.0.20.3:8\0\\appid\\240\0".to_vec(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn header_only_responses_give_up() {
+    let master = MockMaster::spawn(|_, _| vec![page(&[], false)]).await;
+    let mut client = connect(&master).await;
+
+    let (sender, _receiver) = channel(16);
+    let result = client.query(Region::All, Filter::new(), sender).await;
+
+    assert!(matches!(result, Err(MsqError::EmptyResponses(4))));
+    assert_eq!(master.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn query_outcome_empty_and_completed() {
+    let master = MockMaster::scripted(vec![page(&[], true)]).await;
+    let mut client = connect(&master).await;
+    let (sender, _receiver) = channel(16);
+    let outcome = client
+        .query(Region::Africa, Filter::new().appid(240), sender)
+        .await
+        .unwrap();
+    assert_eq!(outcome, QueryOutcome::Empty);
+
+    let master = MockMaster::scripted(vec![page(
+        &[([1, 1, 1, 1], 27015), ([2, 2, 2, 2], 27015)],
+        true,
+    )])
+    .await;
+    let mut client = connect(&master).await;
+    let (sender, _receiver) = channel(16);
+    let outcome = client
+        .query(Region::Europe, Filter::new().appid(240), sender)
+        .await
+        .unwrap();
+    assert_eq!(outcome, QueryOutcome::Completed(2));
+}
+
+#[tokio::test]
+async fn bytes_after_terminator_ignored() {
+    let mut packet = page(&[([1, 1, 1, 1], 27015)], false);
+    // Terminator followed by garbage where its port would be, and more
+    packet.extend_from_slice(&[0, 0, 0, 0, 0x69, 0x87, 0xDE, 0xAD, 0xBE]);
+    let master = MockMaster::scripted(vec![packet]).await;
+    let mut client = connect(&master).await;
+    let (sender, receiver) = channel(16);
+    let outcome = client
+        .query(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+    assert_eq!(outcome, QueryOutcome::Completed(1));
+    assert_eq!(collect(receiver).await, vec![(addr(1, 1, 1, 1), 27015)]);
+    assert_eq!(master.requests().len(), 1);
+
+    // Terminator cut right after its address
+    let mut packet = page(&[([2, 2, 2, 2], 27016)], false);
+    packet.extend_from_slice(&[0, 0, 0, 0]);
+    let master = MockMaster::scripted(vec![packet]).await;
+    let mut client = connect(&master).await;
+    let (sender, receiver) = channel(16);
+    let outcome = client
+        .query(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+    assert_eq!(outcome, QueryOutcome::Completed(1));
+    assert_eq!(collect(receiver).await, vec![(addr(2, 2, 2, 2), 27016)]);
+}
+
+#[tokio::test]
+async fn query_grouped_by_address() {
+    let master = MockMaster::scripted(vec![page(
+        &[
+            ([1, 1, 1, 1], 27015),
+            ([1, 1, 1, 1], 27016),
+            ([2, 2, 2, 2], 27015),
+            ([1, 1, 1, 1], 27017),
+            ([1, 1, 1, 1], 27015),
+        ],
+        true,
+    )])
+    .await;
+    let mut client = connect(&master).await;
+
+    let grouped = client
+        .query_grouped(Region::All, Filter::new().appid(240))
+        .await
+        .unwrap();
+
+    assert_eq!(grouped.len(), 2);
+    assert_eq!(grouped[&addr(1, 1, 1, 1)], vec![27015, 27016, 27017]);
+    assert_eq!(grouped[&addr(2, 2, 2, 2)], vec![27015]);
+}
+
+#[tokio::test]
+async fn query_vec_deduplicates() {
+    let master = MockMaster::scripted(vec![
+        page(&[([1, 1, 1, 1], 27015), ([2, 2, 2, 2], 27015)], false),
+        page(&[([2, 2, 2, 2], 27015), ([1, 1, 1, 1], 27016)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+
+    assert_eq!(
+        servers.as_slice(),
+        [
+            (addr(1, 1, 1, 1), 27015),
+            (addr(2, 2, 2, 2), 27015),
+            (addr(1, 1, 1, 1), 27016),
+        ]
+    );
+    assert_eq!(servers.by_ip()[&addr(1, 1, 1, 1)], vec![27015, 27016]);
+}
+
+#[tokio::test]
+async fn slow_first_packet_with_tuned_timeouts() {
+    let packets = vec![
+        (
+            Duration::from_millis(300),
+            page(&[([1, 1, 1, 1], 27015)], false),
+        ),
+        (Duration::ZERO, page(&[([2, 2, 2, 2], 27015)], true)),
+    ];
+
+    let master = MockMaster::scripted_delayed(packets.clone()).await;
+    let mut client = connect(&master).await;
+    client.set_initial_timeout(Some(Duration::from_secs(5)));
+    client.set_page_timeout(Some(Duration::from_millis(100)));
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+    assert_eq!(servers.len(), 2);
+
+    let master = MockMaster::scripted_delayed(packets).await;
+    let mut client = connect(&master).await;
+    client.set_timeout(Some(Duration::from_millis(100)));
+    let result = client.query_vec(Region::All, Filter::new()).await;
+    assert!(matches!(result, Err(MsqError::Timeout)));
+}
+
+#[tokio::test]
+async fn cancel_during_page_delay() {
+    let master = MockMaster::scripted(vec![page(&[([1, 1, 1, 1], 27015)], false)]).await;
+    let mut client = connect(&master).await;
+    client.set_delay(Duration::from_secs(6));
+    let token = CancellationToken::new();
+    client.set_cancel_token(Some(token.clone()));
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        token.cancel();
+    });
+
+    let started = Instant::now();
+    let (sender, _receiver) = channel(16);
+    let result = client.query(Region::All, Filter::new(), sender).await;
+
+    assert!(matches!(result, Err(MsqError::Cancelled)));
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(master.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn progress_after_each_page() {
+    let master = MockMaster::scripted(vec![
+        page(
+            &[([1, 1, 1, 1], 1), ([1, 1, 1, 1], 2), ([1, 1, 1, 1], 3)],
+            false,
+        ),
+        page(
+            &[([2, 2, 2, 2], 1), ([2, 2, 2, 2], 2), ([2, 2, 2, 2], 3)],
+            false,
+        ),
+        page(&[([3, 3, 3, 3], 1)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+    let (progress_sender, mut progress_receiver) = channel(16);
+    client.set_progress_sender(Some(progress_sender));
+
+    client.query_vec(Region::All, Filter::new()).await.unwrap();
+
+    let mut estimates = vec![];
+    while let Ok(progress) = progress_receiver.try_recv() {
+        estimates.push((
+            progress.pages,
+            progress.servers,
+            progress.estimated_progress(),
+        ));
+    }
+    assert_eq!(estimates, vec![(1, 3, 0.5), (2, 6, 6.0 / 9.0), (3, 7, 1.0)]);
+}
+
+#[tokio::test]
+async fn socket_options() {
+    let master = MockMaster::scripted(vec![page(&[([1, 1, 1, 1], 27015)], true)]).await;
+    let mut client = connect(&master).await;
+    client.set_recv_buffer(1 << 20).unwrap();
+    client.set_ttl(32).unwrap();
+
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+    assert_eq!(servers.as_slice(), [(addr(1, 1, 1, 1), 27015)]);
+}
+
+#[tokio::test]
+async fn query_raw_bytes_rejects_interior_null() {
+    let master = MockMaster::scripted(vec![page(&[], true)]).await;
+    let mut client = connect(&master).await;
+
+    let (sender, _receiver) = channel(16);
+    let result = client
+        .query_raw_bytes(0xFF, b"\\appid\0\\240\0", sender)
+        .await;
+
+    match result {
+        Err(MsqError::Io(err)) => assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput),
+        _ => panic!("expected an InvalidInput error"),
+    }
+    assert!(master.requests().is_empty());
+
+    // The terminator itself is optional
+    let (sender, _receiver) = channel(16);
+    client
+        .query_raw_bytes(0xFF, b"\\appid\\240", sender)
+        .await
+        .unwrap();
+    assert_eq!(
+        master.requests(),
+        vec![b"\x31\xFF0.0.0.0:0\0\\appid\\240\0".to_vec()]
+    );
+}
+
+#[tokio::test]
+async fn reconnect_after_socket_error() {
+    let mut client = MSQClient::new().await.unwrap();
+    assert!(matches!(
+        client.reconnect().await,
+        Err(MsqError::NotConnected)
+    ));
+
+    // Nothing listens there yet, the query fails with a socket error
+    let master_addr = {
+        let placeholder = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        placeholder.local_addr().unwrap().to_string()
+    };
+    client.connect(&master_addr).await.unwrap();
+    client.set_delay(Duration::ZERO);
+    client.set_timeout(Some(Duration::from_millis(500)));
+    let result = client.query_vec(Region::All, Filter::new()).await;
+    assert!(result.is_err());
+
+    let _master =
+        MockMaster::scripted_at(&master_addr, vec![page(&[([1, 1, 1, 1], 27015)], true)]).await;
+    client.reconnect().await.unwrap();
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+    assert_eq!(servers.as_slice(), [(addr(1, 1, 1, 1), 27015)]);
+}
+
+#[tokio::test]
+async fn reconnect_keeps_local_address() {
+    let master = MockMaster::scripted_at(
+        "[::1]:0",
+        vec![
+            page(&[([1, 1, 1, 1], 27015)], true),
+            page(&[([2, 2, 2, 2], 27015)], true),
+        ],
+    )
+    .await;
+    let sock = tokio::net::UdpSocket::bind("[::1]:0").await.unwrap();
+    let mut client = MSQClient::from_socket(sock);
+    client.connect(&master.addr()).await.unwrap();
+    client.set_timeout(Some(Duration::from_secs(2)));
+    client.query_vec(Region::All, Filter::new()).await.unwrap();
+
+    client.reconnect().await.unwrap();
+    let local = client.local_addr().unwrap();
+    assert_eq!(local.ip(), "::1".parse::<std::net::IpAddr>().unwrap());
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+    assert_eq!(servers.as_slice(), [(addr(2, 2, 2, 2), 27015)]);
+}
+
+#[tokio::test]
+async fn query_raw_packets_verbatim() {
+    let packets = vec![
+        page(&[([1, 1, 1, 1], 27015), ([2, 2, 2, 2], 27016)], false),
+        page(&[([3, 3, 3, 3], 27015)], true),
+    ];
+    let master = MockMaster::scripted(packets.clone()).await;
+    let mut client = connect(&master).await;
+
+    let received = client
+        .query_raw_packets(0xFF, "\\appid\\240")
+        .await
+        .unwrap();
+
+    assert_eq!(received, packets);
+    assert_eq!(
+        master.requests(),
+        vec![
+            b"\x31\xFF0.0.0.0:0\0\\appid\\240\0".to_vec(),
+            b"\x31\xFF2.2.2.2:27016\0\\appid\\240\0".to_vec(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn rotate_master_across_addresses() {
+    let first = MockMaster::spawn(|_, _| vec![page(&[([1, 1, 1, 1], 27015)], true)]).await;
+    let second = MockMaster::spawn(|_, _| vec![page(&[([2, 2, 2, 2], 27015)], true)]).await;
+    let addrs = vec![
+        first.addr().parse().unwrap(),
+        second.addr().parse().unwrap(),
+    ];
+
+    let mut client = MSQClient::new().await.unwrap();
+    client.connect(&first.addr()).await.unwrap();
+    client.set_resolver(move |_| Ok(addrs.clone()));
+    client.set_rotate_master(true);
+
+    let mut servers = vec![];
+    for _ in 0..3 {
+        servers.extend(
+            client
+                .query_vec(Region::All, Filter::new())
+                .await
+                .unwrap()
+                .servers,
+        );
+    }
+
+    assert_eq!(
+        servers,
+        vec![
+            (addr(1, 1, 1, 1), 27015),
+            (addr(2, 2, 2, 2), 27015),
+            (addr(1, 1, 1, 1), 27015),
+        ]
+    );
+    assert_eq!(first.requests().len(), 2);
+    assert_eq!(second.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn skip_unknown_packets_mid_stream() {
+    let junk = b"\xFF\xFF\xFF\xFFIsource".to_vec();
+    let truncated = b"\xFF\xFF".to_vec();
+    let packets = vec![
+        vec![page(&[([1, 1, 1, 1], 27015)], false)],
+        vec![junk, truncated, page(&[([2, 2, 2, 2], 27015)], true)],
+    ];
+
+    let responses = packets.clone();
+    let master = MockMaster::spawn(move |index, _| responses[index].clone()).await;
+    let mut client = connect(&master).await;
+    let result = client.query_vec(Region::All, Filter::new()).await;
+    assert!(matches!(result, Err(MsqError::UnexpectedResponse(b'I'))));
+
+    let master = MockMaster::spawn(move |index, _| packets[index].clone()).await;
+    let mut client = connect(&master).await;
+    client.set_skip_unknown_packets(true);
+    client.set_timeout(Some(Duration::from_secs(1)));
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+    assert_eq!(
+        servers.as_slice(),
+        [(addr(1, 1, 1, 1), 27015), (addr(2, 2, 2, 2), 27015)]
+    );
+}
+
+#[tokio::test]
+async fn query_batched_follows_pages() {
+    let master = MockMaster::scripted(vec![
+        page(&[([1, 1, 1, 1], 27015), ([1, 1, 1, 1], 27016)], false),
+        page(&[], false),
+        page(&[([2, 2, 2, 2], 27015)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+
+    let (sender, mut receiver) = channel(16);
+    let outcome = client
+        .query_batched(Region::All, Filter::new().appid(240), sender)
+        .await
+        .unwrap();
+
+    let mut batches = vec![];
+    while let Some(batch) = receiver.recv().await {
+        batches.push(batch);
+    }
+    assert_eq!(
+        batches,
+        vec![
+            vec![(addr(1, 1, 1, 1), 27015), (addr(1, 1, 1, 1), 27016)],
+            vec![(addr(2, 2, 2, 2), 27015)],
+        ]
+    );
+    assert_eq!(outcome, QueryOutcome::Completed(3));
+}
+
+#[tokio::test]
+async fn deadline_bounds_whole_query() {
+    // Every page arrives well within the response timeout, but never ends
+    let master = MockMaster::spawn_delayed(|_, _| {
+        vec![(
+            Duration::from_millis(100),
+            page(&[([1, 1, 1, 1], 27015)], false),
+        )]
+    })
+    .await;
+    let mut client = connect(&master).await;
+    client.set_timeout(Some(Duration::from_secs(1)));
+    client.set_deadline(Some(Duration::from_millis(350)));
+
+    let started = Instant::now();
+    let (sender, receiver) = channel(16);
+    let result = client.query(Region::All, Filter::new(), sender).await;
+
+    assert!(matches!(result, Err(MsqError::Timeout)));
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert!(!collect(receiver).await.is_empty());
+}
+
+#[tokio::test]
+async fn poll_next_page_steps_through_pages() {
+    let master = MockMaster::scripted(vec![
+        page(&[([1, 1, 1, 1], 27015), ([1, 1, 1, 1], 27016)], false),
+        page(&[([2, 2, 2, 2], 27015)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+    assert_eq!(client.poll_next_page().await.unwrap(), None);
+
+    client
+        .start_query(Region::Europe, Filter::new().appid(240))
+        .unwrap();
+    assert!(master.requests().is_empty());
+
+    assert_eq!(
+        client.poll_next_page().await.unwrap(),
+        Some(vec![(addr(1, 1, 1, 1), 27015), (addr(1, 1, 1, 1), 27016)])
+    );
+    assert_eq!(master.requests().len(), 1);
+    assert_eq!(
+        client.poll_next_page().await.unwrap(),
+        Some(vec![(addr(2, 2, 2, 2), 27015)])
+    );
+    assert_eq!(client.poll_next_page().await.unwrap(), None);
+    assert_eq!(
+        master.requests(),
+        vec![
+            b"\x31\x030.0.0.0:0\0\\appid\\240\0".to_vec(),
+            b"\x31\x031.1.1.1:27016\0\\appid\\240\0".to_vec(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn oversized_request_rejected_before_sending() {
+    let master = MockMaster::scripted(vec![page(&[], true)]).await;
+    let mut client = connect(&master).await;
+
+    let tags: Vec<String> = (0..300).map(|i| format!("tag{}", i)).collect();
+    let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+    let (sender, _receiver) = channel(16);
+    let result = client
+        .query(Region::All, Filter::new().gametype(&tags), sender)
+        .await;
+
+    assert!(matches!(result, Err(MsqError::RequestTooLarge(len)) if len > MAX_REQUEST_LEN));
+    assert!(master.requests().is_empty());
+}
+
+#[tokio::test]
+async fn longest_valid_filter_fits_with_challenge() {
+    // Longest seed for the second request, both challenged
+    let master = MockMaster::spawn(|_, request| {
+        if !request.ends_with(&CHALLENGE.to_le_bytes()) {
+            return vec![challenge_packet(CHALLENGE)];
+        }
+        let (_, seed, _) = parse_request(request);
+        vec![page(&[([255, 255, 255, 255], 65535)], seed != "0.0.0.0:0")]
+    })
+    .await;
+    let mut client = connect(&master).await;
+    client.set_timeout(Some(Duration::from_secs(2)));
+
+    let longest = |len: usize| Filter::new().name_match(&"x".repeat(len - "\\name_match\\".len()));
+    assert!(!longest(1372).is_valid());
+    let filter = longest(1371);
+    assert!(filter.is_valid());
+
+    client.query_vec(Region::All, filter).await.unwrap();
+    let requests = master.requests();
+    assert_eq!(requests.last().unwrap().len(), MAX_REQUEST_LEN);
+}
+
+#[tokio::test]
+async fn max_pages_stops_early() {
+    let master = MockMaster::spawn(|index, _| {
+        let octet = index as u8 + 1;
+        vec![page(&[([octet, octet, octet, octet], 27015)], false)]
+    })
+    .await;
+    let mut client = connect(&master).await;
+    client.set_max_pages(Some(3));
+
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+
+    assert_eq!(
+        servers.as_slice(),
+        [
+            (addr(1, 1, 1, 1), 27015),
+            (addr(2, 2, 2, 2), 27015),
+            (addr(3, 3, 3, 3), 27015),
+        ]
+    );
+    assert_eq!(master.requests().len(), 3);
+}
+
+#[cfg(feature = "rdns")]
+#[tokio::test]
+async fn query_with_rdns_receiver_dropped() {
+    let pages = 8;
+    let master = MockMaster::spawn(move |index, _| {
+        let server = ([10, 0, 0, index as u8], 27015);
+        vec![page(&[server], index + 1 == pages)]
+    })
+    .await;
+    let mut client = connect(&master).await;
+    client.set_reverse_resolver(|_| None);
+
+    let (sender, receiver) = channel(1);
+    drop(receiver);
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.query_with_rdns(Region::All, Filter::new(), sender),
+    )
+    .await
+    .expect("the query kept paging for nobody");
+
+    assert!(result.is_ok());
+    assert!(master.requests().len() < pages);
+}
+
+#[cfg(feature = "rdns")]
+#[tokio::test]
+async fn query_with_rdns_attaches_hostnames() {
+    let master = MockMaster::scripted(vec![page(
+        &[
+            ([1, 1, 1, 1], 27015),
+            ([2, 2, 2, 2], 27015),
+            ([1, 1, 1, 1], 27016),
+        ],
+        true,
+    )])
+    .await;
+    let mut client = connect(&master).await;
+    client.set_reverse_resolver(|address| {
+        (address.octets() == [1, 1, 1, 1]).then(|| String::from("one.example.com"))
+    });
+
+    let (sender, mut receiver) = channel(16);
+    client
+        .query_with_rdns(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+
+    let mut servers = vec![];
+    while let Some(server) = receiver.recv().await {
+        servers.push(server);
+    }
+    assert_eq!(
+        servers,
+        vec![
+            (
+                addr(1, 1, 1, 1),
+                27015,
+                Some(String::from("one.example.com"))
+            ),
+            (addr(2, 2, 2, 2), 27015, None),
+            (
+                addr(1, 1, 1, 1),
+                27016,
+                Some(String::from("one.example.com"))
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn from_connected_socket() {
+    let master = MockMaster::scripted(vec![page(&[([1, 1, 1, 1], 27015)], true)]).await;
+    let sock = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    sock.connect(master.addr()).await.unwrap();
+
+    let mut client = MSQClient::from_socket(sock);
+    client.set_delay(Duration::ZERO);
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+    assert_eq!(servers.as_slice(), [(addr(1, 1, 1, 1), 27015)]);
+
+    client.reconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn sorted_results_are_reproducible() {
+    let first = MockMaster::scripted(vec![page(
+        &[
+            ([2, 2, 2, 2], 27015),
+            ([1, 1, 1, 1], 27016),
+            ([1, 1, 1, 1], 27015),
+        ],
+        true,
+    )])
+    .await;
+    let second = MockMaster::scripted(vec![page(
+        &[
+            ([1, 1, 1, 1], 27016),
+            ([1, 1, 1, 1], 27015),
+            ([2, 2, 2, 2], 27015),
+        ],
+        true,
+    )])
+    .await;
+
+    let mut client = connect(&first).await;
+    let first_run = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+    let mut client = connect(&second).await;
+    let second_run = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+
+    assert_ne!(first_run, second_run);
+    assert_eq!(first_run.sorted(), second_run.sorted());
+}
+
+#[tokio::test]
+async fn query_to_writer_emits_lines() {
+    let master = MockMaster::scripted(vec![
+        page(&[([1, 2, 3, 4], 27015), ([10, 0, 0, 1], 8)], false),
+        page(&[([192, 168, 0, 1], 27016)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+
+    let mut output: Vec<u8> = vec![];
+    let outcome = client
+        .query_to_writer(Region::All, Filter::new(), &mut output)
+        .await
+        .unwrap();
+
+    assert_eq!(outcome, QueryOutcome::Completed(3));
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "1.2.3.4:27015\n10.0.0.1:8\n192.168.0.1:27016\n"
+    );
+}
+
+#[tokio::test]
+async fn ping_measures_round_trip() {
+    let master = MockMaster::scripted_delayed(vec![(
+        Duration::from_millis(50),
+        page(&[([1, 1, 1, 1], 27015)], false),
+    )])
+    .await;
+    let mut client = connect(&master).await;
+    client.set_timeout(Some(Duration::from_secs(2)));
+
+    let rtt = client.ping().await.unwrap();
+
+    assert!(rtt >= Duration::from_millis(50));
+    assert!(rtt < Duration::from_secs(2));
+    assert_eq!(master.requests(), vec![b"\x31\xFF0.0.0.0:0\0\0".to_vec()]);
+}
+
+#[tokio::test]
+async fn ping_answers_challenge_with_query_settings() {
+    let master = MockMaster::spawn(|_, request| {
+        if !request.ends_with(&CHALLENGE.to_le_bytes()) {
+            return vec![challenge_packet(CHALLENGE)];
+        }
+        vec![page(&[([1, 1, 1, 1], 27015)], false)]
+    })
+    .await;
+    let mut client = connect(&master).await;
+    client.set_timeout(Some(Duration::from_secs(2)));
+    client.set_strict(true);
+
+    // Unfiltered in strict mode, nothing sent
+    assert!(matches!(
+        client.ping().await,
+        Err(MsqError::UnfilteredQuery)
+    ));
+    assert!(master.requests().is_empty());
+
+    client.set_region(Region::Europe);
+    client.set_filter(Filter::new().appid(240));
+    client.ping().await.unwrap();
+
+    let requests = master.requests();
+    assert_eq!(requests.len(), 2);
+    for request in &requests {
+        let (region, seed, filter) = parse_request(request);
+        assert_eq!(region, Region::Europe.as_u8());
+        assert_eq!(seed, "0.0.0.0:0");
+        assert_eq!(filter, "\\appid\\240");
+    }
+    assert!(requests[1].ends_with(&CHALLENGE.to_le_bytes()));
+}
+
+#[tokio::test]
+async fn master_on_non_standard_port() {
+    let master_addr = {
+        let placeholder = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        placeholder.local_addr().unwrap()
+    };
+    assert_ne!(master_addr.port(), 27011);
+    let master = MockMaster::scripted_at(
+        &master_addr.to_string(),
+        vec![
+            page(&[([1, 1, 1, 1], 27015)], false),
+            page(&[([2, 2, 2, 2], 27015)], true),
+        ],
+    )
+    .await;
+    let mut client = connect(&master).await;
+
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+
+    assert_eq!(
+        servers.as_slice(),
+        [(addr(1, 1, 1, 1), 27015), (addr(2, 2, 2, 2), 27015)]
+    );
+    assert_eq!(master.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn adaptive_delay_backs_off_on_empty_pages() {
+    let packets = vec![
+        page(&[([1, 1, 1, 1], 27015)], false),
+        page(&[], false),
+        page(&[], false),
+        page(&[([2, 2, 2, 2], 27015)], false),
+        page(&[([3, 3, 3, 3], 27015)], true),
+    ];
+
+    let master = MockMaster::scripted(packets.clone()).await;
+    let mut client = connect(&master).await;
+    client.set_delay(Duration::from_millis(50));
+    let started = Instant::now();
+    client.query_vec(Region::All, Filter::new()).await.unwrap();
+    let fixed = started.elapsed();
+
+    let master = MockMaster::scripted(packets).await;
+    let mut client = connect(&master).await;
+    client.set_delay(Duration::from_millis(50));
+    client.set_adaptive_delay(true);
+    let started = Instant::now();
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+    let adaptive = started.elapsed();
+
+    // Fixed: 4 x 50ms, adaptive: 50 + 100 + 200 + 50ms (reset after a full page)
+    assert_eq!(servers.len(), 3);
+    assert!(fixed >= Duration::from_millis(200));
+    assert!(adaptive >= Duration::from_millis(400));
+    assert!(adaptive < Duration::from_millis(1000));
+}
+
+#[tokio::test]
+async fn run_with_stored_region_and_filter() {
+    let master = MockMaster::spawn(|_, _| vec![page(&[([1, 1, 1, 1], 27015)], true)]).await;
+    let mut client = connect(&master).await;
+    client.set_region(Region::Europe);
+    client.set_filter(Filter::new().appid(240));
+
+    for _ in 0..2 {
+        let (sender, receiver) = channel(16);
+        client.run(sender).await.unwrap();
+        assert_eq!(collect(receiver).await, vec![(addr(1, 1, 1, 1), 27015)]);
+    }
+    assert_eq!(
+        master.requests(),
+        vec![
+            b"\x31\x030.0.0.0:0\0\\appid\\240\0".to_vec(),
+            b"\x31\x030.0.0.0:0\0\\appid\\240\0".to_vec(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn scrape_sweeps_every_region() {
+    let mut europe_requests = 0;
+    let master = MockMaster::spawn(move |_, request| {
+        let (region, _, filter) = parse_request(request);
+        assert_eq!(filter, "\\appid\\240");
+        match region {
+            // USEast and All overlap
+            0x00 => vec![page(&[([1, 1, 1, 1], 27015), ([2, 2, 2, 2], 27015)], true)],
+            // Europe drops the first request
+            0x03 => {
+                europe_requests += 1;
+                if europe_requests == 1 {
+                    vec![]
+                } else {
+                    vec![page(&[([3, 3, 3, 3], 27015)], true)]
+                }
+            }
+            // Asia never answers
+            0x04 => vec![],
+            0xFF => vec![page(&[([2, 2, 2, 2], 27015), ([9, 9, 9, 9], 27015)], true)],
+            _ => vec![page(&[], true)],
+        }
+    })
+    .await;
+    let mut client = connect(&master).await;
+    client.set_timeout(Some(Duration::from_millis(100)));
+
+    let servers = client.scrape(240).await.unwrap();
+    assert_eq!(
+        servers.as_slice(),
+        [
+            (addr(1, 1, 1, 1), 27015),
+            (addr(2, 2, 2, 2), 27015),
+            (addr(3, 3, 3, 3), 27015),
+            (addr(9, 9, 9, 9), 27015),
+        ]
+    );
+
+    let regions: Vec<u8> = master
+        .requests()
+        .iter()
+        .map(|request| parse_request(request).0)
+        .collect();
+    assert_eq!(
+        regions,
+        vec![0x00, 0x01, 0x02, 0x03, 0x03, 0x04, 0x04, 0x04, 0x05, 0x06, 0x07, 0xFF]
+    );
+}
+
+#[tokio::test]
+async fn error_sent_through_channel() {
+    let master = MockMaster::scripted(vec![page(&[([1, 1, 1, 1], 27015)], false)]).await;
+    let mut client = connect(&master).await;
+    client.set_timeout(Some(Duration::from_millis(100)));
+    let (sender, mut receiver) = channel(16);
+    let result = client.query(Region::All, Filter::new(), sender).await;
+    assert!(matches!(result, Err(MsqError::Timeout)));
+
+    assert!(matches!(
+        receiver.recv().await,
+        Some(Ok((address, 27015))) if address == addr(1, 1, 1, 1)
+    ));
+    assert!(matches!(
+        receiver.recv().await,
+        Some(Err(MsqError::Timeout))
+    ));
+    assert!(receiver.recv().await.is_none());
+}
+
+// Time is paused and jumps forward whenever the client sleeps, so the
+// default 6 seconds delay between pages costs nothing
+#[tokio::test(start_paused = true)]
+async fn paging_with_default_delay_under_paused_time() {
+    let master = MockMaster::scripted(vec![
+        page(&[([1, 1, 1, 1], 27015)], false),
+        page(&[([2, 2, 2, 2], 27015)], false),
+        page(&[([3, 3, 3, 3], 27015)], true),
+    ])
+    .await;
+    let mut client = MSQClient::new().await.unwrap();
+    client.connect(&master.addr()).await.unwrap();
+
+    let started = tokio::time::Instant::now();
+    let real_started = std::time::Instant::now();
+    let (sender, receiver) = channel(16);
+    let outcome = client
+        .query(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+
+    assert_eq!(outcome, QueryOutcome::Completed(3));
+    assert_eq!(collect(receiver).await.len(), 3);
+    assert!(started.elapsed() >= Duration::from_secs(12));
+    assert!(real_started.elapsed() < Duration::from_secs(1));
+}
+
+// The limit on empty responses only counts once the delay is capped, so the
+// backoff gets to 60 seconds with the default delay
+#[tokio::test(start_paused = true)]
+async fn adaptive_delay_reaches_cap() {
+    let master = MockMaster::spawn(|index, _| match index {
+        0 => vec![page(&[([1, 1, 1, 1], 27015)], false)],
+        1..=7 => vec![page(&[], false)],
+        _ => vec![page(&[([2, 2, 2, 2], 27015)], true)],
+    })
+    .await;
+    let mut client = MSQClient::new().await.unwrap();
+    client.connect(&master.addr()).await.unwrap();
+    client.set_adaptive_delay(true);
+
+    let started = tokio::time::Instant::now();
+    let result = client.query_vec(Region::All, Filter::new()).await.unwrap();
+
+    // 6 after the first page, then 12, 24, 48 and 60 x 4 after 7 empty pages
+    assert_eq!(result.servers.len(), 2);
+    assert_eq!(master.requests().len(), 9);
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_secs(330));
+    assert!(elapsed < Duration::from_secs(331));
+
+    // One more empty page at the capped delay gives up
+    let master = MockMaster::spawn(|index, _| match index {
+        0 => vec![page(&[([1, 1, 1, 1], 27015)], false)],
+        _ => vec![page(&[], false)],
+    })
+    .await;
+    client.connect(&master.addr()).await.unwrap();
+    let result = client.query_vec(Region::All, Filter::new()).await;
+    assert!(matches!(result, Err(MsqError::EmptyResponses(8))));
+    assert_eq!(master.requests().len(), 9);
+}
+
+#[tokio::test]
+async fn completion_after_terminator() {
+    let master = MockMaster::spawn(|index, _| match index {
+        1 => vec![page(&[([3, 3, 3, 3], 27016)], true)],
+        _ => vec![page(&[([1, 1, 1, 1], 27015), ([2, 2, 2, 2], 27015)], false)],
+    })
+    .await;
+    let mut client = connect(&master).await;
+    let (completion_sender, mut completion_receiver) = channel(1);
+    client.set_completion_sender(Some(completion_sender));
+
+    let (sender, _receiver) = channel(16);
+    client
+        .query(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+    assert_eq!(
+        completion_receiver.try_recv().unwrap(),
+        QueryComplete {
+            count: 3,
+            last_seed: (addr(3, 3, 3, 3), 27016),
+        }
+    );
+
+    // Nothing for a query stopped before the end of the list
+    client.set_max_pages(Some(1));
+    let (sender, _receiver) = channel(16);
+    client
+        .query(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+    assert!(completion_receiver.try_recv().is_err());
+}
+
+#[cfg(feature = "replay")]
+#[tokio::test]
+async fn record_then_replay() {
+    let master = MockMaster::scripted(vec![
+        page(&[([1, 1, 1, 1], 27015), ([2, 2, 2, 2], 27015)], false),
+        page(&[([3, 3, 3, 3], 27016)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+    let path = std::env::temp_dir().join(format!("msq-record-{}.log", std::process::id()));
+    client.set_record_path(Some(&path)).unwrap();
+
+    let (sender, receiver) = channel(16);
+    client
+        .query(Region::Europe, Filter::new().appid(240), sender)
+        .await
+        .unwrap();
+    client.set_record_path(None::<&str>).unwrap();
+    let servers = collect(receiver).await;
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    let directions: Vec<&str> = log.lines().map(|line| &line[..1]).collect();
+    assert_eq!(directions, vec![">", "<", ">", "<"]);
+    assert_eq!(msq::replay(&path).unwrap(), servers);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn query_raw_borrowed_or_owned_filter() {
+    let master = MockMaster::spawn(|_, _| vec![page(&[([1, 1, 1, 1], 27015)], true)]).await;
+    let mut client = connect(&master).await;
+
+    let (sender, _receiver) = channel(16);
+    client
+        .query_raw(0x03, "\\appid\\240", sender)
+        .await
+        .unwrap();
+    let (sender, _receiver) = channel(16);
+    let owned = Filter::new().appid(240).as_string();
+    client.query_raw(0x03, owned, sender).await.unwrap();
+
+    let requests = master.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0], requests[1]);
+    assert_eq!(parse_request(&requests[1]).2, "\\appid\\240");
+}
+
+#[tokio::test]
+async fn strict_mode_refuses_unfiltered_query() {
+    let master = MockMaster::spawn(|_, _| vec![page(&[([1, 1, 1, 1], 27015)], true)]).await;
+    let mut client = connect(&master).await;
+    client.set_strict(true);
+
+    let (sender, _receiver) = channel(16);
+    let result = client.query(Region::All, Filter::new(), sender).await;
+    assert!(matches!(result, Err(MsqError::UnfilteredQuery)));
+    assert!(master.requests().is_empty());
+
+    // Either a region or a filter is enough
+    let (sender, _receiver) = channel(16);
+    client
+        .query(Region::Europe, Filter::new(), sender)
+        .await
+        .unwrap();
+    let (sender, _receiver) = channel(16);
+    client
+        .query(Region::All, Filter::new().appid(240), sender)
+        .await
+        .unwrap();
+
+    client.set_strict(false);
+    let (sender, _receiver) = channel(16);
+    client
+        .query(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+    assert_eq!(master.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn addresses_and_ports_in_network_order() {
+    let mut packet = common::HEADER.to_vec();
+    packet.extend_from_slice(&[192, 168, 0, 1, 0x1F, 0x90]);
+    packet.extend_from_slice(&[10, 0, 0, 255, 0xFF, 0xFE]);
+    packet.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+    let master = MockMaster::scripted(vec![packet]).await;
+    let mut client = connect(&master).await;
+
+    let (sender, receiver) = channel(16);
+    client
+        .query(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+    assert_eq!(
+        collect(receiver).await,
+        vec![(addr(192, 168, 0, 1), 8080), (addr(10, 0, 0, 255), 65534)]
+    );
+}
+
+#[cfg(feature = "socks5")]
+#[tokio::test]
+async fn query_through_socks5_proxy() {
+    let master = MockMaster::scripted(vec![
+        page(&[([1, 1, 1, 1], 27015)], false),
+        page(&[([2, 2, 2, 2], 27016)], true),
+    ])
+    .await;
+    let proxy = common::spawn_socks5().await;
+    let mut client = MSQClient::new().await.unwrap();
+    client.set_delay(Duration::ZERO);
+    client.set_timeout(Some(Duration::from_secs(1)));
+    client
+        .connect_via_proxy(&proxy, &master.addr())
+        .await
+        .unwrap();
+
+    let (sender, receiver) = channel(16);
+    client
+        .query(Region::All, Filter::new().appid(240), sender)
+        .await
+        .unwrap();
+    assert_eq!(
+        collect(receiver).await,
+        vec![(addr(1, 1, 1, 1), 27015), (addr(2, 2, 2, 2), 27016)]
+    );
+    // Requests reach the master unwrapped
+    let requests = master.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(parse_request(&requests[1]).1, "1.1.1.1:27015");
+}
+
+#[cfg(feature = "socks5")]
+#[tokio::test]
+async fn socks5_proxy_uses_custom_resolver() {
+    let master = MockMaster::scripted(vec![page(&[([1, 1, 1, 1], 27015)], true)]).await;
+    let master_addr: std::net::SocketAddr = master.addr().parse().unwrap();
+    let proxy = common::spawn_socks5().await;
+    let mut client = MSQClient::new().await.unwrap();
+    client.set_delay(Duration::ZERO);
+    client.set_timeout(Some(Duration::from_secs(1)));
+    client.set_resolver(move |host| {
+        assert_eq!(host, "master.example:27011");
+        Ok(vec![master_addr])
+    });
+    client
+        .connect_via_proxy(&proxy, "master.example:27011")
+        .await
+        .unwrap();
+
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+    assert_eq!(servers.as_slice(), [(addr(1, 1, 1, 1), 27015)]);
+}
+
+#[tokio::test]
+async fn custom_response_header() {
+    let fork_page = |servers: &[([u8; 4], u16)], terminate| {
+        let mut packet = page(servers, terminate);
+        packet.splice(..6, [0xFF, 0xFF, 0xFF, 0xFF, 0x67, 0x0A]);
+        packet
+    };
+    let master = MockMaster::spawn(move |index, _| match index {
+        2 => vec![fork_page(&[([2, 2, 2, 2], 27015)], true)],
+        _ => vec![fork_page(&[([1, 1, 1, 1], 27015)], false)],
+    })
+    .await;
+    let mut client = connect(&master).await;
+
+    let (sender, _receiver) = channel(16);
+    let result = client.query(Region::All, Filter::new(), sender).await;
+    assert!(matches!(result, Err(MsqError::UnexpectedResponse(0x67))));
+
+    client.set_response_header(vec![0xFF, 0xFF, 0xFF, 0xFF, 0x67, 0x0A]);
+    let (sender, receiver) = channel(16);
+    client
+        .query(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+    assert_eq!(
+        collect(receiver).await,
+        vec![(addr(1, 1, 1, 1), 27015), (addr(2, 2, 2, 2), 27015)]
+    );
+}
+
+#[tokio::test]
+async fn approximate_dedup_across_appids() {
+    let servers: Vec<([u8; 4], u16)> = (0..200u16)
+        .map(|i| ([10, 0, (i >> 8) as u8, i as u8], 27015))
+        .collect();
+    let master = MockMaster::spawn(move |_, _| vec![page(&servers, true)]).await;
+    let mut client = connect(&master).await;
+    let mode = DedupMode::Approximate {
+        capacity: 1000,
+        fp_rate: 0.01,
+    };
+    client.set_dedup_mode(mode);
+    assert!(mode.memory_bound().unwrap() <= 2048);
+
+    let (sender, receiver) = channel(1024);
+    let outcome = client
+        .query_appids(Region::All, &[240, 440, 730], Filter::new(), sender)
+        .await
+        .unwrap();
+    let received = collect(receiver).await;
+
+    // Every repeat dropped, at most a few unique servers lost
+    assert_eq!(master.requests().len(), 3);
+    assert_eq!(outcome.servers(), received.len());
+    assert!(received.len() <= 200 && received.len() >= 190);
+    let unique: std::collections::HashSet<_> = received.iter().collect();
+    assert_eq!(unique.len(), received.len());
+}
+
+#[tokio::test]
+async fn query_vec_in_subnet_filters_client_side() {
+    let master = MockMaster::scripted(vec![
+        page(
+            &[([203, 0, 113, 1], 27015), ([203, 0, 114, 1], 27015)],
+            false,
+        ),
+        page(&[([203, 0, 113, 255], 27016), ([10, 0, 0, 1], 27015)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+
+    let servers = client
+        .query_vec_in_subnet(Region::All, Filter::new(), "203.0.113.0/24")
+        .await
+        .unwrap();
+    assert_eq!(
+        servers.as_slice(),
+        [
+            (addr(203, 0, 113, 1), 27015),
+            (addr(203, 0, 113, 255), 27016)
+        ]
+    );
+    // Every page is still requested
+    assert_eq!(master.requests().len(), 2);
+
+    let result = client
+        .query_vec_in_subnet(Region::All, Filter::new(), "203.0.113.0/33")
+        .await;
+    assert!(matches!(result, Err(MsqError::InvalidAddress)));
+    assert_eq!(master.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn query_vec_empty_vs_truncated() {
+    // The master ends the list right away: nothing matches
+    let master = MockMaster::scripted(vec![page(&[], true)]).await;
+    let mut client = connect(&master).await;
+    let result = client.query_vec(Region::All, Filter::new()).await.unwrap();
+    assert!(result.servers.is_empty());
+    assert_eq!(result.pages, 1);
+    assert!(result.terminated);
+
+    // Only keepalive pages before the page limit: the list is incomplete
+    let master = MockMaster::spawn(|_, _| vec![page(&[], false)]).await;
+    let mut client = connect(&master).await;
+    client.set_delay(Duration::ZERO);
+    client.set_max_pages(Some(2));
+    let result = client.query_vec(Region::All, Filter::new()).await.unwrap();
+    assert!(result.servers.is_empty());
+    assert_eq!(result.pages, 2);
+    assert!(!result.terminated);
+}
+
+#[tokio::test]
+async fn skip_delay_after_full_pages() {
+    async fn enumerate(page_size: usize) -> (usize, Duration) {
+        let master = MockMaster::spawn(move |index, _| {
+            let servers: Vec<([u8; 4], u16)> = (0..page_size)
+                .map(|i| ([10, index as u8, (i >> 8) as u8, i as u8], 27015))
+                .collect();
+            vec![page(&servers, index == 2)]
+        })
+        .await;
+        let mut client = connect(&master).await;
+        client.set_delay(Duration::from_millis(300));
+        client.set_skip_delay_when_full(true);
+
+        let started = Instant::now();
+        let result = client.query_vec(Region::All, Filter::new()).await.unwrap();
+        (result.servers.len(), started.elapsed())
+    }
+
+    let (full_count, full) = enumerate(231).await;
+    let (partial_count, partial) = enumerate(100).await;
+
+    assert_eq!(full_count, 3 * 231);
+    assert_eq!(partial_count, 3 * 100);
+    assert!(full < Duration::from_millis(300));
+    assert!(partial >= Duration::from_millis(600));
+}
+
+#[tokio::test]
+async fn challenge_packet_reported() {
+    // A challenge without its number can't be answered
+    let master = MockMaster::scripted(vec![b"\xFF\xFF\xFF\xFFA\x4B\xA1".to_vec()]).await;
+    let mut client = connect(&master).await;
+
+    let result = client.query_vec(Region::All, Filter::new()).await;
+    match result {
+        Err(err @ MsqError::UnexpectedResponse(0x41)) => {
+            assert!(err.to_string().contains("a challenge"))
+        }
+        _ => panic!("expected an UnexpectedResponse error"),
+    }
+
+    // Anything else is still an unknown header
+    let master = MockMaster::scripted(vec![b"\x00\x01\x02\x03\x66\x0A".to_vec()]).await;
+    let mut client = connect(&master).await;
+    let result = client.query_vec(Region::All, Filter::new()).await;
+    assert!(matches!(result, Err(MsqError::MismatchedHeader)));
+}
+
+#[tokio::test]
+async fn query_page_stream_yields_pages() {
+    let master = MockMaster::scripted(vec![
+        page(&[([1, 1, 1, 1], 27015), ([2, 2, 2, 2], 27015)], false),
+        page(&[], false),
+        page(&[([3, 3, 3, 3], 27015)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+    client.set_delay(Duration::ZERO);
+
+    let mut stream = pin!(client.query_page_stream(Region::All, Filter::new()));
+    let mut pages = vec![];
+    while let Some(page) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+        pages.push(page.unwrap());
+    }
+
+    // The keepalive page is skipped
+    assert_eq!(
+        pages,
+        vec![
+            vec![(addr(1, 1, 1, 1), 27015), (addr(2, 2, 2, 2), 27015)],
+            vec![(addr(3, 3, 3, 3), 27015)],
+        ]
+    );
+    assert_eq!(master.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn query_with_options() {
+    let master = MockMaster::spawn(|index, _| match index {
+        0 => vec![page(
+            &[
+                ([1, 1, 1, 1], 27015),
+                ([2, 2, 2, 2], 27015),
+                ([1, 1, 1, 1], 27015),
+            ],
+            false,
+        )],
+        // Lost, requested again after the timeout
+        1 => vec![],
+        2 => vec![page(
+            &[
+                ([3, 3, 3, 3], 27015),
+                ([4, 4, 4, 4], 27015),
+                ([5, 5, 5, 5], 27015),
+            ],
+            false,
+        )],
+        _ => vec![page(&[([6, 6, 6, 6], 27015)], true)],
+    })
+    .await;
+    let mut client = connect(&master).await;
+    let opts = QueryOpts {
+        delay: Duration::ZERO,
+        timeout: Some(Duration::from_millis(200)),
+        retries: 1,
+        max_results: Some(4),
+        dedup: Some(DedupMode::Exact),
+    };
+
+    let (sender, receiver) = channel(16);
+    let stats = client
+        .query_with(Region::All, Filter::new().appid(240), opts, sender)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        collect(receiver).await,
+        vec![
+            (addr(1, 1, 1, 1), 27015),
+            (addr(2, 2, 2, 2), 27015),
+            (addr(3, 3, 3, 3), 27015),
+            (addr(4, 4, 4, 4), 27015),
+        ]
+    );
+    assert_eq!(stats.servers, 4);
+    assert_eq!(stats.duplicates, 1);
+    assert_eq!(stats.pages, 2);
+    assert_eq!(stats.retries, 1);
+    assert!(!stats.terminated);
+    assert!(stats.elapsed >= Duration::from_millis(200));
+    let requests = master.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[1], requests[2]);
+
+    // Out of retries, the timeout fails the query
+    let master = MockMaster::scripted(vec![page(&[([1, 1, 1, 1], 27015)], false)]).await;
+    let mut client = connect(&master).await;
+    let (sender, _receiver) = channel(16);
+    let result = client
+        .query_with(Region::All, Filter::new(), opts, sender)
+        .await;
+    assert!(matches!(result, Err(MsqError::Timeout)));
+    assert_eq!(master.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn connect_prefers_socket_family() {
+    let master = MockMaster::scripted(vec![page(&[([1, 1, 1, 1], 27015)], true)]).await;
+    let ipv4: std::net::SocketAddr = master.addr().parse().unwrap();
+    let ipv6: std::net::SocketAddr = format!("[2001:db8::1]:{}", ipv4.port()).parse().unwrap();
+
+    // Resolved IPv6 first, the IPv4 socket connects to the IPv4 address
+    let mut client = MSQClient::new().await.unwrap();
+    client.set_resolver(move |host| {
+        assert_eq!(host, "master.example:27011");
+        Ok(vec![ipv6, ipv4])
+    });
+    client.connect("master.example:27011").await.unwrap();
+    let servers = client
+        .query_vec(Region::All, Filter::new())
+        .await
+        .unwrap()
+        .servers;
+    assert_eq!(servers.as_slice(), [(addr(1, 1, 1, 1), 27015)]);
+
+    let mut client = MSQClient::new().await.unwrap();
+    client.set_resolver(move |_| Ok(vec![ipv6]));
+    let result = client.connect("master.example:27011").await;
+    assert!(
+        matches!(result, Err(MsqError::Io(err)) if err.kind() == std::io::ErrorKind::AddrNotAvailable)
+    );
+}
+
+#[tokio::test]
+async fn malformed_filter_rejected_before_sending() {
+    let master = MockMaster::spawn(|_, _| vec![page(&[([1, 1, 1, 1], 27015)], true)]).await;
+    let mut client = connect(&master).await;
+
+    // The nor block is never closed
+    let filter = Filter::new().appid(240).nor().map("de_dust2");
+    let (sender, receiver) = channel(16);
+    let result = client.query(Region::All, filter.clone(), sender).await;
+    assert!(matches!(result, Err(MsqError::MalformedFilter)));
+    assert!(collect(receiver).await.is_empty());
+
+    // The `\` in the value reads back as another token
+    let (sender, _receiver) = channel(16);
+    let result = client
+        .query(Region::All, Filter::new().map("a\\b\\c"), sender)
+        .await;
+    assert!(matches!(result, Err(MsqError::MalformedFilter)));
+
+    let (sender, _receiver) = channel(16);
+    let result = client
+        .query_raw(0xFF, "\\appid\\240\\nor\\2\\map", sender)
+        .await;
+    assert!(matches!(result, Err(MsqError::MalformedFilter)));
+    assert!(master.requests().is_empty());
+
+    client.set_validate(false);
+    let (sender, receiver) = channel(16);
+    client.query(Region::All, filter, sender).await.unwrap();
+    assert_eq!(collect(receiver).await, vec![(addr(1, 1, 1, 1), 27015)]);
+    assert_eq!(master.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn byte_counts_follow_queries() {
+    let master = MockMaster::scripted(vec![
+        page(&[([1, 1, 1, 1], 27015)], false),
+        page(&[([2, 2, 2, 2], 27015)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+
+    client.query_vec(Region::All, Filter::new()).await.unwrap();
+
+    // Requests: 2 + `0.0.0.0:0\0` + `\0`, then 2 + `1.1.1.1:27015\0` + `\0`
+    let sent: usize = master.requests().iter().map(Vec::len).sum();
+    assert_eq!(sent, 13 + 17);
+    assert_eq!(client.bytes_sent(), 30);
+    // Pages: header + 1 server, then header + 1 server + terminator
+    assert_eq!(client.bytes_received(), 12 + 18);
+
+    client.reset_byte_counts();
+    assert_eq!(client.bytes_sent(), 0);
+    assert_eq!(client.bytes_received(), 0);
+}
+
+const CHALLENGE: u32 = 0x330C_A14B;
+
+fn challenge_packet(number: u32) -> Vec<u8> {
+    let mut packet = b"\xFF\xFF\xFF\xFFA".to_vec();
+    packet.extend_from_slice(&number.to_le_bytes());
+    packet
+}
+
+#[tokio::test]
+async fn challenge_handshake() {
+    // Only answers requests carrying the challenge number
+    let master = MockMaster::spawn(|_, request| {
+        if !request.ends_with(&CHALLENGE.to_le_bytes()) {
+            return vec![challenge_packet(CHALLENGE)];
+        }
+        let (_, seed, _) = parse_request(request);
+        if seed == "0.0.0.0:0" {
+            vec![page(&[([1, 1, 1, 1], 27015)], false)]
+        } else {
+            vec![page(&[([2, 2, 2, 2], 27015)], true)]
+        }
+    })
+    .await;
+    let mut client = connect(&master).await;
+    client.set_delay(Duration::ZERO);
+    client.set_timeout(Some(Duration::from_secs(2)));
+
+    let result = client
+        .query_vec(Region::All, Filter::new().appid(240))
+        .await
+        .unwrap();
+    assert_eq!(result.servers.len(), 2);
+    assert!(result.terminated);
+    assert_eq!(result.pages, 2);
+
+    // The first request again with the number, then every later one
+    let requests = master.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(!requests[0].ends_with(&CHALLENGE.to_le_bytes()));
+    assert_eq!(requests[1][..requests[0].len()], requests[0][..]);
+    for request in &requests[1..] {
+        let (_, _, filter) = parse_request(request);
+        assert_eq!(filter, "\\appid\\240");
+        assert!(request.ends_with(&CHALLENGE.to_le_bytes()));
+    }
+}
+
+#[tokio::test]
+async fn challenge_repeated_gives_up() {
+    let master = MockMaster::spawn(|index, _| vec![challenge_packet(index as u32)]).await;
+    let mut client = connect(&master).await;
+    client.set_timeout(Some(Duration::from_secs(2)));
+
+    let result = client.query_vec(Region::All, Filter::new()).await;
+    assert!(matches!(result, Err(MsqError::UnexpectedResponse(0x41))));
+    // The request and 3 answers
+    assert_eq!(master.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn max_ports_per_ip() {
+    let busy = [1, 1, 1, 1];
+    let master = MockMaster::scripted(vec![
+        page(
+            &[
+                (busy, 27015),
+                (busy, 27016),
+                ([2, 2, 2, 2], 27015),
+                (busy, 27017),
+            ],
+            false,
+        ),
+        page(&[(busy, 27018), (busy, 27015), ([3, 3, 3, 3], 27015)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+    client.set_delay(Duration::ZERO);
+    client.set_max_ports_per_ip(Some(2));
+
+    let result = client.query_vec(Region::All, Filter::new()).await.unwrap();
+    let by_ip = result.servers.by_ip();
+    assert_eq!(by_ip[&"1.1.1.1".parse().unwrap()], vec![27015, 27016]);
+    assert_eq!(by_ip.len(), 3);
+    assert!(result.terminated);
+
+    // Still paged from the last server of the page, dropped or not
+    let (_, seed, _) = parse_request(&master.requests()[1]);
+    assert_eq!(seed, "1.1.1.1:27017");
+}
+
+#[tokio::test]
+async fn query_with_smallest_channel() {
+    // More pages than the client holds while the channel is full
+    let pages = 12;
+    let master = MockMaster::spawn(move |index, _| {
+        let server = ([10, 0, 0, index as u8], 27015);
+        vec![page(&[server], index + 1 == pages)]
+    })
+    .await;
+    let mut client = connect(&master).await;
+    client.set_timeout(Some(Duration::from_secs(2)));
+    client.set_send_timeout(Some(Duration::from_secs(1)));
+
+    // The smallest capacity, read while the query runs
+    let (sender, receiver) = channel(1);
+    let consumer = tokio::spawn(collect(receiver));
+    let outcome = client
+        .query(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+    assert_eq!(outcome.servers(), pages);
+    assert_eq!(master.requests().len(), pages);
+    assert_eq!(consumer.await.unwrap().len(), pages);
+}
+
+#[tokio::test]
+async fn query_read_after_return_needs_whole_list_capacity() {
+    let pages = 12;
+    let master = MockMaster::spawn(move |index, _| {
+        let server = ([10, 0, 0, (index % pages) as u8], 27015);
+        vec![page(&[server], (index + 1) % pages == 0)]
+    })
+    .await;
+    let mut client = connect(&master).await;
+    client.set_timeout(Some(Duration::from_secs(2)));
+    client.set_send_timeout(Some(Duration::from_millis(200)));
+
+    // Every server fits, all of them are in the channel on return
+    let (sender, receiver) = channel(pages);
+    client
+        .query(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+    assert_eq!(collect(receiver).await.len(), pages);
+
+    // One short, the query stalls on the last server and gives up
+    let (sender, receiver) = channel(pages - 1);
+    let result = client.query(Region::All, Filter::new(), sender).await;
+    assert!(matches!(result, Err(MsqError::ChannelStalled)));
+    assert_eq!(collect(receiver).await.len(), pages - 1);
+}
+
+#[tokio::test]
+async fn query_batched_receiver_dropped() {
+    let master = MockMaster::scripted(vec![
+        page(&[([1, 1, 1, 1], 27015)], false),
+        page(&[([2, 2, 2, 2], 27015)], false),
+        page(&[([3, 3, 3, 3], 27015)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+
+    let (sender, receiver) = channel(4);
+    drop(receiver);
+    let outcome = client
+        .query_batched(Region::All, Filter::new(), sender)
+        .await
+        .unwrap();
+
+    // Stopped at the first page instead of paging for nobody
+    assert_eq!(outcome, QueryOutcome::Empty);
+    assert_eq!(master.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn query_with_max_results_on_page_boundary() {
+    let master = MockMaster::scripted(vec![
+        page(&[([1, 1, 1, 1], 27015), ([2, 2, 2, 2], 27015)], false),
+        page(&[([3, 3, 3, 3], 27015)], true),
+    ])
+    .await;
+    let mut client = connect(&master).await;
+    let opts = QueryOpts {
+        // Would be waited before requesting the second page
+        delay: Duration::from_secs(5),
+        timeout: Some(Duration::from_secs(2)),
+        max_results: Some(2),
+        ..QueryOpts::default()
+    };
+
+    let started = Instant::now();
+    let (sender, receiver) = channel(16);
+    let stats = client
+        .query_with(Region::All, Filter::new(), opts, sender)
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(stats.servers, 2);
+    assert!(!stats.terminated);
+    assert_eq!(collect(receiver).await.len(), 2);
+    assert_eq!(master.requests().len(), 1);
+
+    // No page at all for no server
+    let opts = QueryOpts {
+        max_results: Some(0),
+        ..opts
+    };
+    let (sender, _receiver) = channel(16);
+    let stats = client
+        .query_with(Region::All, Filter::new(), opts, sender)
+        .await
+        .unwrap();
+    assert_eq!(stats.pages, 0);
+    assert_eq!(master.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn query_receiver_dropped_stops_paging() {
+    let pages = 8;
+    let master = MockMaster::spawn(move |index, _| {
+        let server = ([10, 0, 0, index as u8], 27015);
+        vec![page(&[server], index + 1 == pages)]
+    })
+    .await;
+    let mut client = connect(&master).await;
+    client.set_timeout(Some(Duration::from_secs(2)));
+
+    let (sender, receiver) = channel(1);
+    drop(receiver);
+    let result = client.query(Region::All, Filter::new(), sender).await;
+
+    assert!(result.is_ok());
+    assert_eq!(master.requests().len(), 1);
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "non-async")]
+
+use msq::{Filter, MSQClientBlock, MsqError, Region};
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+#[test]
+fn timeout_on_silent_master() {
+    // Bound but never answers
+    let master = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut client = MSQClientBlock::new().unwrap();
+    client
+        .connect(&master.local_addr().unwrap().to_string())
+        .unwrap();
+    client
+        .set_timeout(Some(Duration::from_millis(200)))
+        .unwrap();
+
+    let started = Instant::now();
+    let result = client.query(Region::All, Filter::new().appid(240));
+
+    assert!(matches!(result, Err(MsqError::Timeout)));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn oversized_filter_error() {
+    let master = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut client = MSQClientBlock::new().unwrap();
+    client
+        .connect(&master.local_addr().unwrap().to_string())
+        .unwrap();
+
+    let filter = Filter::new().name_match(&"x".repeat(2000));
+    let result = client.query(Region::All, filter);
+
+    match result {
+        Err(err @ MsqError::RequestTooLarge(_)) => {
+            assert!(err.to_string().contains("shorten the filter"))
+        }
+        _ => panic!("expected a RequestTooLarge error"),
+    }
+}
+
+#[test]
+fn from_connected_socket() {
+    // Answers the first request with a terminated page of one server
+    let master = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let master_addr = master.local_addr().unwrap();
+    let responder = std::thread::spawn(move || {
+        let mut buf = [0u8; 2048];
+        let (_, peer) = master.recv_from(&mut buf).unwrap();
+        let packet = [
+            0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x0A, 1, 2, 3, 4, 0x69, 0x87, 0, 0, 0, 0, 0, 0,
+        ];
+        master.send_to(&packet, peer).unwrap();
+    });
+
+    let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+    sock.connect(master_addr).unwrap();
+    let bound = sock.local_addr().unwrap();
+    let mut client = MSQClientBlock::from_socket(sock);
+    client.set_timeout(Some(Duration::from_secs(2))).unwrap();
+    assert_eq!(client.local_addr().unwrap(), bound);
+
+    let servers = client.query(Region::All, Filter::new()).unwrap();
+    responder.join().unwrap();
+    assert_eq!(servers, vec![("1.2.3.4".parse().unwrap(), 27015)]);
+}
+
+#[test]
+fn challenge_handshake() {
+    // Challenges the first request, then ends the list
+    let master = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let master_addr = master.local_addr().unwrap();
+    let responder = std::thread::spawn(move || {
+        let mut buf = [0u8; 2048];
+        let (_, peer) = master.recv_from(&mut buf).unwrap();
+        master
+            .send_to(
+                &[0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x4B, 0xA1, 0x0C, 0x33],
+                peer,
+            )
+            .unwrap();
+
+        let (len, peer) = master.recv_from(&mut buf).unwrap();
+        let packet = [
+            0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x0A, 1, 2, 3, 4, 0x69, 0x87, 0, 0, 0, 0, 0, 0,
+        ];
+        master.send_to(&packet, peer).unwrap();
+        buf[..len].to_vec()
+    });
+
+    let mut client = MSQClientBlock::new().unwrap();
+    client.connect(&master_addr.to_string()).unwrap();
+    client.set_timeout(Some(Duration::from_secs(2))).unwrap();
+
+    let servers = client.query(Region::All, Filter::new()).unwrap();
+    let answer = responder.join().unwrap();
+    assert_eq!(servers, vec![("1.2.3.4".parse().unwrap(), 27015)]);
+    assert!(answer.ends_with(&[0x4B, 0xA1, 0x0C, 0x33]));
+}
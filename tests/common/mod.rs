@@ -0,0 +1,175 @@
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::time::sleep;
+
+pub const HEADER: [u8; 6] = [0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x0A];
+
+/// A local master server answering requests with scripted packets
+pub struct MockMaster {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl MockMaster {
+    /// Spawn a mock master that replies to the n-th request (starting at 0)
+    /// with every packet the responder returns for it
+    pub async fn spawn<F>(mut responder: F) -> MockMaster
+    where
+        F: FnMut(usize, &[u8]) -> Vec<Vec<u8>> + Send + 'static,
+    {
+        Self::spawn_delayed(move |index, request| {
+            responder(index, request)
+                .into_iter()
+                .map(|packet| (Duration::ZERO, packet))
+                .collect()
+        })
+        .await
+    }
+
+    /// Same as `spawn`, but each packet is sent after its paired delay
+    pub async fn spawn_delayed<F>(responder: F) -> MockMaster
+    where
+        F: FnMut(usize, &[u8]) -> Vec<(Duration, Vec<u8>)> + Send + 'static,
+    {
+        Self::spawn_at("127.0.0.1:0", responder).await
+    }
+
+    /// Same as `spawn_delayed`, but bound to the given address
+    pub async fn spawn_at<F>(bind_addr: &str, mut responder: F) -> MockMaster
+    where
+        F: FnMut(usize, &[u8]) -> Vec<(Duration, Vec<u8>)> + Send + 'static,
+    {
+        let sock = UdpSocket::bind(bind_addr).await.unwrap();
+        let addr = sock.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 2048];
+            loop {
+                let (len, peer) = match sock.recv_from(&mut buf).await {
+                    Ok(recv) => recv,
+                    Err(_) => continue,
+                };
+                let index = {
+                    let mut recorded = recorded.lock().unwrap();
+                    recorded.push(buf[..len].to_vec());
+                    recorded.len() - 1
+                };
+                for (delay, packet) in responder(index, &buf[..len]) {
+                    sleep(delay).await;
+                    let _ = sock.send_to(&packet, peer).await;
+                }
+            }
+        });
+
+        MockMaster { addr, requests }
+    }
+
+    /// Spawn a mock master that replies to the n-th request with the n-th
+    /// packet and ignores any request past the end of the script
+    pub async fn scripted(packets: Vec<Vec<u8>>) -> MockMaster {
+        Self::spawn(move |index, _| packets.get(index).cloned().into_iter().collect()).await
+    }
+
+    /// Same as `scripted`, but each packet is sent after its paired delay
+    pub async fn scripted_delayed(packets: Vec<(Duration, Vec<u8>)>) -> MockMaster {
+        Self::spawn_delayed(move |index, _| packets.get(index).cloned().into_iter().collect()).await
+    }
+
+    /// Same as `scripted`, but bound to the given address
+    pub async fn scripted_at(bind_addr: &str, packets: Vec<Vec<u8>>) -> MockMaster {
+        Self::spawn_at(bind_addr, move |index, _| {
+            packets
+                .get(index)
+                .map(|packet| (Duration::ZERO, packet.clone()))
+                .into_iter()
+                .collect()
+        })
+        .await
+    }
+
+    pub fn addr(&self) -> String {
+        self.addr.to_string()
+    }
+
+    /// Every request received so far, in order
+    pub fn requests(&self) -> Vec<Vec<u8>> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Spawn a SOCKS5 proxy accepting one UDP association (IPv4 only), returns
+/// its TCP address
+pub async fn spawn_socks5() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let relay = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let relay_port = relay.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        let (mut control, _) = listener.accept().await.unwrap();
+        let mut greeting = [0u8; 3];
+        control.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(greeting, [5, 1, 0]);
+        control.write_all(&[5, 0]).await.unwrap();
+
+        let mut request = [0u8; 10];
+        control.read_exact(&mut request).await.unwrap();
+        assert_eq!(request[..2], [5, 3]);
+        // Unspecified relay address: same host as the proxy
+        let mut reply = vec![5, 0, 0, 1, 0, 0, 0, 0];
+        reply.extend_from_slice(&relay_port.to_be_bytes());
+        control.write_all(&reply).await.unwrap();
+
+        let mut client = None;
+        let mut buf = [0u8; 2048];
+        loop {
+            let (len, peer) = relay.recv_from(&mut buf).await.unwrap();
+            if client.is_none() || client == Some(peer) {
+                client = Some(peer);
+                assert_eq!(buf[..4], [0, 0, 0, 1]);
+                let target = SocketAddr::from((
+                    [buf[4], buf[5], buf[6], buf[7]],
+                    u16::from_be_bytes([buf[8], buf[9]]),
+                ));
+                relay.send_to(&buf[10..len], target).await.unwrap();
+            } else if let (Some(client), SocketAddr::V4(from)) = (client, peer) {
+                let mut datagram = vec![0, 0, 0, 1];
+                datagram.extend_from_slice(&from.ip().octets());
+                datagram.extend_from_slice(&from.port().to_be_bytes());
+                datagram.extend_from_slice(&buf[..len]);
+                relay.send_to(&datagram, client).await.unwrap();
+            }
+        }
+    });
+
+    addr
+}
+
+/// Build a server list response packet, optionally terminated by `0.0.0.0:0`
+pub fn page(servers: &[([u8; 4], u16)], terminate: bool) -> Vec<u8> {
+    let mut packet = HEADER.to_vec();
+    for (octets, port) in servers {
+        packet.extend_from_slice(octets);
+        packet.extend_from_slice(&port.to_be_bytes());
+    }
+    if terminate {
+        packet.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+    }
+    packet
+}
+
+/// Split a request packet into its region code, seed and filter strings
+pub fn parse_request(request: &[u8]) -> (u8, String, String) {
+    assert_eq!(request[0], 0x31);
+    let mut fields = request[2..].split(|b| *b == 0x00);
+    let seed = String::from_utf8(fields.next().unwrap().to_vec()).unwrap();
+    let filter = String::from_utf8(fields.next().unwrap().to_vec()).unwrap();
+    (request[1], seed, filter)
+}
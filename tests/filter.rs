@@ -0,0 +1,240 @@
+#![cfg(feature = "alloc")]
+
+use msq::{Filter, Game, MsqError, Region, ServerType};
+use proptest::prelude::*;
+
+/// A single builder call
+///
+/// Left out on purpose:
+/// * Values containing `\` (or `\0`): the builder writes values as-is, so
+///   they add tokens of their own and can't read back as the same filter.
+/// * Special filters left open: `as_string` only holds closed ones, the
+///   sequences are always closed before comparing.
+#[derive(Debug, Clone)]
+enum Call {
+    Appid(u32),
+    Napp(u32),
+    Map(String),
+    Gamedir(String),
+    NameMatch(String),
+    VersionMatch(String),
+    Gameaddr(String),
+    Gametype(Vec<String>),
+    Gamedata(Vec<String>),
+    Gamedataor(Vec<String>),
+    Dedicated(bool),
+    Secure(bool),
+    Linux(bool),
+    Password(bool),
+    Full(bool),
+    Proxy(bool),
+    Empty(bool),
+    Whitelisted(bool),
+    CollapseAddrHash(bool),
+    ServerType(bool),
+    Region(u8),
+    Without(&'static str),
+    Nor,
+    Nand,
+    And,
+    End,
+}
+
+fn value() -> impl Strategy<Value = String> {
+    "[a-z0-9_.*:,]{0,12}"
+}
+
+fn call() -> impl Strategy<Value = Call> {
+    prop_oneof![
+        any::<u32>().prop_map(Call::Appid),
+        any::<u32>().prop_map(Call::Napp),
+        value().prop_map(Call::Map),
+        value().prop_map(Call::Gamedir),
+        value().prop_map(Call::NameMatch),
+        value().prop_map(Call::VersionMatch),
+        value().prop_map(Call::Gameaddr),
+        prop::collection::vec(value(), 0..4).prop_map(Call::Gametype),
+        prop::collection::vec(value(), 0..4).prop_map(Call::Gamedata),
+        prop::collection::vec(value(), 0..4).prop_map(Call::Gamedataor),
+        any::<bool>().prop_map(Call::Dedicated),
+        any::<bool>().prop_map(Call::Secure),
+        any::<bool>().prop_map(Call::Linux),
+        any::<bool>().prop_map(Call::Password),
+        any::<bool>().prop_map(Call::Full),
+        any::<bool>().prop_map(Call::Proxy),
+        any::<bool>().prop_map(Call::Empty),
+        any::<bool>().prop_map(Call::Whitelisted),
+        any::<bool>().prop_map(Call::CollapseAddrHash),
+        any::<bool>().prop_map(Call::ServerType),
+        (0u8..=8).prop_map(Call::Region),
+        prop::sample::select(vec!["appid", "map", "nor", "gametype"]).prop_map(Call::Without),
+        Just(Call::Nor),
+        Just(Call::Nand),
+        Just(Call::And),
+        Just(Call::End),
+        Just(Call::End),
+    ]
+}
+
+fn tags(tags: &[String]) -> Vec<&str> {
+    tags.iter().map(String::as_str).collect()
+}
+
+fn build(calls: &[Call]) -> Filter {
+    let mut filter = Filter::new();
+    let mut open = 0;
+    for call in calls {
+        filter = match call {
+            Call::Appid(appid) => filter.appid(*appid),
+            Call::Napp(appid) => filter.napp(*appid),
+            Call::Map(map) => filter.map(map),
+            Call::Gamedir(dir) => filter.gamedir(dir),
+            Call::NameMatch(name) => filter.name_match(name),
+            Call::VersionMatch(version) => filter.version_match(version),
+            Call::Gameaddr(addr) => filter.gameaddr(addr),
+            Call::Gametype(t) => filter.gametype(&tags(t)),
+            Call::Gamedata(t) => filter.gamedata(&tags(t)),
+            Call::Gamedataor(t) => filter.gamedataor(&tags(t)),
+            Call::Dedicated(on) => filter.dedicated(*on),
+            Call::Secure(on) => filter.secure(*on),
+            Call::Linux(on) => filter.linux(*on),
+            Call::Password(on) => filter.password(*on),
+            Call::Full(on) => filter.full(*on),
+            Call::Proxy(on) => filter.proxy(*on),
+            Call::Empty(on) => filter.empty(*on),
+            Call::Whitelisted(on) => filter.whitelisted(*on),
+            Call::CollapseAddrHash(on) => filter.collapse_addr_hash(*on),
+            Call::ServerType(dedicated) => filter.server_type(if *dedicated {
+                ServerType::Dedicated
+            } else {
+                ServerType::Proxy
+            }),
+            Call::Region(code) => match Region::from_u8(*code) {
+                Ok(region) => filter.region(region),
+                Err(_) => filter.raw_region(*code),
+            },
+            // Only removes top level tokens
+            Call::Without(key) if open == 0 => filter.without(key),
+            Call::Without(_) => filter,
+            Call::Nor => {
+                open += 1;
+                filter.nor()
+            }
+            Call::Nand => {
+                open += 1;
+                filter.nand()
+            }
+            Call::And => {
+                open += 1;
+                filter.and()
+            }
+            Call::End if open > 0 => {
+                open -= 1;
+                filter.end()
+            }
+            Call::End => filter,
+        };
+    }
+    for _ in 0..open {
+        filter = filter.end();
+    }
+    filter
+}
+
+proptest! {
+    #[test]
+    fn as_string_round_trips(calls in prop::collection::vec(call(), 0..24)) {
+        let filter = build(&calls);
+        let string = filter.as_string();
+
+        let parsed: Filter = string.parse().unwrap();
+        prop_assert_eq!(parsed.as_string(), string);
+        prop_assert!(parsed == filter);
+    }
+}
+
+#[test]
+fn appid_value_reads_back() {
+    assert_eq!(
+        Filter::new().appid(240).linux(true).appid_value(),
+        Some(240)
+    );
+    assert_eq!(Filter::new().game(Game::Tf2).appid_value(), Some(440));
+
+    let parsed: Filter = "\\secure\\1\\appid\\730\\nand\\1\\appid\\240"
+        .parse()
+        .unwrap();
+    assert_eq!(parsed.appid_value(), Some(730));
+}
+
+#[test]
+fn and_group_inside_special() {
+    // Only the servers matching both are excluded
+    let filter = Filter::new().nor().and().map("a").linux(true).end().end();
+    assert_eq!(
+        filter.as_string(),
+        "\\nor\\4\\nor\\3\\nand\\2\\map\\a\\linux\\1"
+    );
+
+    // Nested groups are one group, flattened at top level
+    let filter = Filter::new()
+        .nand()
+        .secure(true)
+        .and()
+        .and()
+        .map("a")
+        .end()
+        .full(true)
+        .end()
+        .end()
+        .and()
+        .appid(240)
+        .end();
+    assert_eq!(
+        filter.as_string(),
+        "\\nand\\5\\secure\\1\\nor\\3\\nand\\2\\map\\a\\full\\0\\appid\\240"
+    );
+
+    // An empty group adds nothing
+    let filter = Filter::new().nor().empty(true).and().end().end();
+    assert_eq!(filter.as_string(), "\\nor\\1\\noplayers\\1");
+    assert!(filter.is_valid());
+}
+
+#[test]
+fn parse_rejects_empty_special() {
+    for string in [
+        "\\nor\\0",
+        "\\appid\\240\\nand\\0",
+        "\\nor\\2\\nand\\0\\map\\x",
+    ] {
+        assert!(
+            matches!(string.parse::<Filter>(), Err(MsqError::MalformedFilter)),
+            "{}",
+            string
+        );
+    }
+
+    // The builder leaves empty blocks out
+    let filter = Filter::new().appid(240).nor().nand().end().end();
+    assert_eq!(filter.as_string(), "\\appid\\240");
+    assert!(filter.is_valid());
+}
+
+#[test]
+fn parse_rejects_and_key() {
+    for string in ["\\and\\1\\map\\x", "\\appid\\240\\nor\\2\\and\\1\\map\\x"] {
+        assert!(
+            matches!(string.parse::<Filter>(), Err(MsqError::MalformedFilter)),
+            "{}",
+            string
+        );
+    }
+}
+
+#[test]
+fn unmatched_end_is_no_op() {
+    let filter = Filter::new().appid(240).end().nor().map("x").end().end();
+    assert_eq!(filter.as_string(), "\\appid\\240\\nor\\1\\map\\x");
+    assert!(filter.is_valid());
+}